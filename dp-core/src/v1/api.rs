@@ -96,8 +96,12 @@ impl_error! {
         InvalidToken(60_002) = (StatusCode::UNAUTHORIZED, "Invalid or expired authorization token"),
         Forbidden(60_003) = (StatusCode::FORBIDDEN, "Not enough rights to access resource"),
         NoAccess(60_004) = (StatusCode::FORBIDDEN, "Not enough scopes to access resource"),
+        Suspended(60_005) = (StatusCode::FORBIDDEN, "Account is suspended"),
+        InvalidCredentials(60_006) = (StatusCode::UNAUTHORIZED, "Invalid username or password"),
 
         Obsolete(70_001) = (StatusCode::NOT_FOUND, "Outdated API version"),
+
+        Internal(80_001) = (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
     }
 }
 
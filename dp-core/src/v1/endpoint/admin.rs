@@ -0,0 +1,198 @@
+//! Administration of server, all endpoints require
+//! [`UserTokenScope::ADMIN`](crate::v1::user::UserTokenScope::ADMIN)
+
+use serde::{Deserialize, Serialize};
+
+use crate::v1::user::{UserRole, UserTy};
+
+use super::{Endpoint, HTTPMethod};
+
+pub const PREFIX: &str = "/admin";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct UserListQuery {
+    /// Part of username
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub limit: u32,
+    #[serde(default)]
+    pub skip: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserInfo {
+    pub id: i64,
+    pub ty: UserTy,
+    pub role: UserRole,
+    pub username: String,
+    pub telegram_id: Option<i64>,
+    pub suspended_at: Option<i64>,
+    pub suspension_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserPath {
+    pub id: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminProjectPath {
+    pub id: i64,
+}
+
+/// Partial update of user, missing fields are not changed
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateUserBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ty: Option<UserTy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<UserRole>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SuspendUserBody {
+    /// Reason shown to user in [`Suspended`](crate::v1::api::Error::Suspended)
+    /// error
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Stats {
+    pub users: i64,
+    /// Users that are not [`UserTy::Normal`]
+    pub pending_users: i64,
+    pub projects: i64,
+    pub sources: i64,
+    pub queued_builds: i64,
+    pub running_builds: i64,
+    /// Size of papers directory in bytes
+    pub disk_usage: u64,
+}
+
+pub struct ListUsers;
+impl Endpoint for ListUsers {
+    type Body = ();
+    type Query = UserListQuery;
+    type Response = Vec<UserInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/users"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Changes type or role of user. Fails with
+/// [`Conflict`](crate::v1::api::Error::Conflict) if administrator demotes
+/// themselves or the last administrator.
+pub struct UpdateUser(pub UserPath);
+impl Endpoint for UpdateUser {
+    type Body = UpdateUserBody;
+    type Query = ();
+    type Response = UserInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Patch
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}", self.0.id)
+    }
+}
+
+/// Suspends account, its tokens are rejected with
+/// [`Suspended`](crate::v1::api::Error::Suspended) until it is enabled
+pub struct DisableUser(pub UserPath);
+impl Endpoint for DisableUser {
+    type Body = SuspendUserBody;
+    type Query = ();
+    type Response = UserInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id/disable"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}/disable", self.0.id)
+    }
+}
+
+pub struct EnableUser(pub UserPath);
+impl Endpoint for EnableUser {
+    type Body = ();
+    type Query = ();
+    type Response = UserInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id/enable"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}/enable", self.0.id)
+    }
+}
+
+/// Revokes all tokens of user
+pub struct RevokeUserTokens(pub UserPath);
+impl Endpoint for RevokeUserTokens {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id/tokens"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}/tokens", self.0.id)
+    }
+}
+
+/// Deletes any project with all its sources
+pub struct DeleteAnyProject(pub AdminProjectPath);
+impl Endpoint for DeleteAnyProject {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/projects/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/projects/{}", self.0.id)
+    }
+}
+
+pub struct GetStats;
+impl Endpoint for GetStats {
+    type Body = ();
+    type Query = ();
+    type Response = Stats;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/stats"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
@@ -4,7 +4,7 @@ use crate::v1::user::UserTokenTy;
 
 use super::{Endpoint, HTTPMethod};
 
-pub const PREFIX: &'static str = "/auth";
+pub const PREFIX: &str = "/auth";
 
 #[derive(Serialize, Deserialize)]
 pub struct IssueUserTokenQuery {
@@ -18,13 +18,66 @@ pub struct IssueUserTokenResponse {
     pub user_id: i64,
     pub token: String,
     pub ty: UserTokenTy,
+    /// Token used to get new pair of tokens with [`RefreshUserToken`], set
+    /// if server issues [`UserTokenTy::Access`] tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_expires_at: Option<i64>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RefreshUserTokenBody {
+    pub user_id: i64,
+    pub refresh_token: String,
+}
+
+/// Invite claim, user should set `telegram_id` or `password` to be able to
+/// log in later
 #[derive(Serialize, Deserialize)]
 pub struct ClaimInviteBody {
     pub invite: String,
     pub username: String,
-    pub telegram_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct OidcAuthorizeQuery {
+    /// Invite claimed if user signs up, otherwise new user is
+    /// [`UserTy::Unverified`](crate::v1::user::UserTy::Unverified)
+    #[serde(default)]
+    pub invite: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OidcAuthorizeResponse {
+    /// Login page of identity provider
+    pub url: String,
+    pub state: String,
+}
+
+/// Parameters passed by identity provider to `redirect_uri`
+#[derive(Serialize, Deserialize)]
+pub struct OidcCallbackBody {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PasswordLoginBody {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SetPasswordBody {
+    pub password: String,
+    /// Required if password is already set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_password: Option<String>,
 }
 
 pub struct TelegramIssueToken;
@@ -90,3 +143,99 @@ impl Endpoint for ClaimInviteTelegram {
         format!("{PREFIX}{}", Self::partial_path())
     }
 }
+
+/// Logs in with username and password, issues the same tokens as login with
+/// Telegram
+pub struct PasswordLogin;
+impl Endpoint for PasswordLogin {
+    type Body = PasswordLoginBody;
+    type Query = ();
+    type Response = IssueUserTokenResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/password"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Sets or changes password of authorized user, requires
+/// [`UserTokenScope::MANAGE_TOKENS`](crate::v1::user::UserTokenScope::MANAGE_TOKENS)
+pub struct SetPassword;
+impl Endpoint for SetPassword {
+    type Body = SetPasswordBody;
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/password"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Starts login with OpenID Connect identity provider, user should be sent
+/// to returned URL. Sets `HttpOnly` cookie with `state`, so login can be
+/// finished only from the same browser.
+pub struct OidcAuthorize;
+impl Endpoint for OidcAuthorize {
+    type Body = ();
+    type Query = OidcAuthorizeQuery;
+    type Response = OidcAuthorizeResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/oidc"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Finishes login with identity provider, requires cookie set by
+/// [`OidcAuthorize`]. User is created on first login.
+pub struct OidcCallback;
+impl Endpoint for OidcCallback {
+    type Body = OidcCallbackBody;
+    type Query = ();
+    type Response = IssueUserTokenResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/oidc"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Exchanges refresh token for new access and refresh tokens. Refresh
+/// token can be used only once, reuse revokes all tokens issued with it.
+pub struct RefreshUserToken;
+impl Endpoint for RefreshUserToken {
+    type Body = RefreshUserTokenBody;
+    type Query = ();
+    type Response = IssueUserTokenResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/refresh"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::v1::user::UserTy;
+
+use super::{Endpoint, HTTPMethod};
+
+pub const PREFIX: &str = "/invites";
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateInviteBody {
+    pub reason: String,
+    /// How many users can register with invite, defaults to 1
+    #[serde(default)]
+    pub max_uses: Option<i64>,
+    /// Lifetime in milliseconds, defaults to and is limited by lifetime of
+    /// invites set by server
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InvitePath {
+    pub id: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InviteInfo {
+    pub id: i64,
+    /// Type of users registered with invite
+    pub user_ty: UserTy,
+    pub reason: String,
+    pub invite: String,
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+    pub max_uses: i64,
+    pub uses: i64,
+    pub revoked_at: Option<i64>,
+}
+
+/// Lists invites issued by user
+pub struct ListInvites;
+impl Endpoint for ListInvites {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<InviteInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/"
+    }
+    fn build_path(&self) -> String {
+        PREFIX.to_owned()
+    }
+}
+
+/// Issues invite, only [`UserTy::Normal`] users can issue invites within
+/// quota set by server. Users registered with such invites are
+/// [`UserTy::Unverified`].
+pub struct CreateInvite;
+impl Endpoint for CreateInvite {
+    type Body = CreateInviteBody;
+    type Query = ();
+    type Response = InviteInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/"
+    }
+    fn build_path(&self) -> String {
+        PREFIX.to_owned()
+    }
+}
+
+/// Revokes invite, users already registered with it are kept
+pub struct RevokeInvite(pub InvitePath);
+impl Endpoint for RevokeInvite {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}", self.0.id)
+    }
+}
@@ -1,8 +1,12 @@
 //! Endpoints models
 
+pub mod admin;
 pub mod auth;
+pub mod invites;
+pub mod moderation;
 pub mod projects;
 pub mod user;
+pub mod webhooks;
 
 /// HTTP method of endpoint
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::v1::user::UserTy;
+
+use super::{Endpoint, HTTPMethod};
+
+pub const PREFIX: &str = "/moderation";
+
+/// User waiting for approval
+#[derive(Serialize, Deserialize)]
+pub struct PendingUser {
+    pub id: i64,
+    pub username: String,
+    pub ty: UserTy,
+    pub invite_reason: Option<String>,
+    /// Username of user that issued invite, `None` for invites issued by
+    /// server administrator
+    pub invited_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PendingUserPath {
+    pub id: i64,
+}
+
+/// Lists users that are not [`UserTy::Normal`]
+pub struct ListPendingUsers;
+impl Endpoint for ListPendingUsers {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<PendingUser>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/users"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Makes user [`UserTy::Normal`]
+pub struct ApproveUser(pub PendingUserPath);
+impl Endpoint for ApproveUser {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id/approve"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}/approve", self.0.id)
+    }
+}
+
+/// Deletes pending user with all its tokens, so username and Telegram
+/// account can be registered again
+pub struct RejectUser(pub PendingUserPath);
+impl Endpoint for RejectUser {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/users/:id/reject"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/users/{}/reject", self.0.id)
+    }
+}
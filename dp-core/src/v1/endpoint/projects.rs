@@ -1,16 +1,49 @@
 use serde::{Deserialize, Serialize};
 
-use crate::v1::project::ProjectTy;
+use crate::v1::{
+    generic::deserialize_some,
+    project::{BuildStatus, ProjectRole, ProjectTy, ProjectVisibility, SourceFormat},
+};
 
 use super::{Endpoint, HTTPMethod};
 
-pub const PREFIX: &'static str = "/projects";
+pub const PREFIX: &str = "/projects";
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateProjectBody {
     pub title: String,
     #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
+    pub ty: ProjectTy,
+    /// Entrypoint if it differs from [`ProjectTy::default_entrypoint`]
+    #[serde(default)]
+    pub entrypoint: Option<String>,
+    #[serde(default)]
+    pub visibility: ProjectVisibility,
+}
+
+/// Partial update of project, missing fields are not changed
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateProjectBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// `Some(None)` (`null`) removes description
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<Option<String>>,
+    /// `Some(None)` (`null`) resets entrypoint to default
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entrypoint: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<ProjectVisibility>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -20,6 +53,8 @@ pub struct ProjectInfo {
     pub title: String,
     pub description: Option<String>,
     pub author_id: i64,
+    pub entrypoint: Option<String>,
+    pub visibility: ProjectVisibility,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -30,11 +65,73 @@ pub struct ProjectListQuery {
     pub skip: u32,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct TelegramProjectListQuery {
+    pub telegram_id: i64,
+    #[serde(default)]
+    pub limit: u32,
+    #[serde(default)]
+    pub skip: u32,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ProjectPath {
     pub id: i64,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct SourcePath {
+    pub id: i64,
+    pub source_id: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadSourceQuery {
+    pub format: SourceFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SourceInfo {
+    pub id: i64,
+    pub project_id: i64,
+    pub created_at: i64,
+    pub format: SourceFormat,
+    pub size: i64,
+    pub uploader_id: Option<i64>,
+    pub build_status: BuildStatus,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MemberPath {
+    pub id: i64,
+    pub username: String,
+}
+
+/// Adds user to project or changes role of existing member
+#[derive(Serialize, Deserialize)]
+pub struct AddMemberBody {
+    pub username: String,
+    pub role: ProjectRole,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MemberInfo {
+    pub user_id: i64,
+    pub username: String,
+    pub role: ProjectRole,
+    pub added_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub source_id: i64,
+    pub status: BuildStatus,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    /// Exit code of compiler, if it was started and exited normally
+    pub exit_code: Option<i64>,
+}
+
 pub struct ListProjects;
 impl Endpoint for ListProjects {
     type Body = ();
@@ -52,6 +149,61 @@ impl Endpoint for ListProjects {
     }
 }
 
+/// Lists projects with [`ProjectVisibility::Public`], doesn't require
+/// authorization
+pub struct ListPublicProjects;
+impl Endpoint for ListPublicProjects {
+    type Body = ();
+    type Query = ProjectListQuery;
+    type Response = Vec<ProjectInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/public"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Lists projects of user with linked Telegram account, requests should be
+/// signed by Telegram bot
+pub struct ListTelegramProjects;
+impl Endpoint for ListTelegramProjects {
+    type Body = ();
+    type Query = TelegramProjectListQuery;
+    type Response = Vec<ProjectInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/telegram"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+pub struct GetProject(pub ProjectPath);
+impl Endpoint for GetProject {
+    type Body = ();
+    type Query = ();
+    type Response = ProjectInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}", self.0.id)
+    }
+}
+
 pub struct CreateProject;
 impl Endpoint for CreateProject {
     type Body = CreateProjectBody;
@@ -69,6 +221,23 @@ impl Endpoint for CreateProject {
     }
 }
 
+pub struct UpdateProject(pub ProjectPath);
+impl Endpoint for UpdateProject {
+    type Body = UpdateProjectBody;
+    type Query = ();
+    type Response = ProjectInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Patch
+    }
+    fn partial_path() -> &'static str {
+        "/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}", self.0.id)
+    }
+}
+
 pub struct DeleteProject(pub ProjectPath);
 impl Endpoint for DeleteProject {
     type Body = ();
@@ -85,3 +254,203 @@ impl Endpoint for DeleteProject {
         format!("{PREFIX}/{}", self.0.id)
     }
 }
+
+pub struct UploadSource(pub ProjectPath);
+impl Endpoint for UploadSource {
+    /// Raw archive in format specified by query
+    type Body = Vec<u8>;
+    type Query = UploadSourceQuery;
+    type Response = SourceInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources", self.0.id)
+    }
+}
+
+pub struct ListSources(pub ProjectPath);
+impl Endpoint for ListSources {
+    type Body = ();
+    type Query = ProjectListQuery;
+    type Response = Vec<SourceInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources", self.0.id)
+    }
+}
+
+pub struct DownloadSource(pub SourcePath);
+impl Endpoint for DownloadSource {
+    type Body = ();
+    type Query = ();
+    /// Raw archive in format of [`SourceInfo::format`]
+    type Response = Vec<u8>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources/:source_id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources/{}", self.0.id, self.0.source_id)
+    }
+}
+
+pub struct GetBuild(pub SourcePath);
+impl Endpoint for GetBuild {
+    type Body = ();
+    type Query = ();
+    type Response = BuildInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources/:source_id/build"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources/{}/build", self.0.id, self.0.source_id)
+    }
+}
+
+/// Queues build of source revision again
+pub struct StartBuild(pub SourcePath);
+impl Endpoint for StartBuild {
+    type Body = ();
+    type Query = ();
+    type Response = BuildInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources/:source_id/build"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources/{}/build", self.0.id, self.0.source_id)
+    }
+}
+
+pub struct GetBuildLog(pub SourcePath);
+impl Endpoint for GetBuildLog {
+    type Body = ();
+    type Query = ();
+    /// Plain text output of compiler
+    type Response = String;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources/:source_id/build/log"
+    }
+    fn build_path(&self) -> String {
+        format!(
+            "{PREFIX}/{}/sources/{}/build/log",
+            self.0.id, self.0.source_id
+        )
+    }
+}
+
+/// PDF of the latest successful build of project. Supports `Range` and
+/// conditional requests.
+pub struct GetProjectPdf(pub ProjectPath);
+impl Endpoint for GetProjectPdf {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<u8>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/pdf"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/pdf", self.0.id)
+    }
+}
+
+/// PDF of source revision. Supports `Range` and conditional requests.
+pub struct GetSourcePdf(pub SourcePath);
+impl Endpoint for GetSourcePdf {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<u8>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/sources/:source_id/pdf"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/sources/{}/pdf", self.0.id, self.0.source_id)
+    }
+}
+
+pub struct ListMembers(pub ProjectPath);
+impl Endpoint for ListMembers {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<MemberInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/members"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/members", self.0.id)
+    }
+}
+
+/// Requires [`ProjectRole::Owner`]
+pub struct AddMember(pub ProjectPath);
+impl Endpoint for AddMember {
+    type Body = AddMemberBody;
+    type Query = ();
+    type Response = MemberInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/:id/members"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/members", self.0.id)
+    }
+}
+
+/// Requires [`ProjectRole::Owner`], except when member leaves project.
+/// The last owner can't be removed.
+pub struct RemoveMember(pub MemberPath);
+impl Endpoint for RemoveMember {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/:id/members/:username"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/members/{}", self.0.id, self.0.username)
+    }
+}
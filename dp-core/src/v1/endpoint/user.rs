@@ -1,10 +1,16 @@
 use serde::{Deserialize, Serialize};
 
-use crate::v1::user::User;
+use crate::v1::{
+    generic::deserialize_some,
+    notification::NotificationKinds,
+    user::{User, UserProfile, UserTokenScope, UserTokenTy},
+};
+
+use super::auth::IssueUserTokenResponse;
 
 use super::{Endpoint, HTTPMethod};
 
-pub const PREFIX: &'static str = "/user";
+pub const PREFIX: &str = "/user";
 
 #[derive(Serialize, Deserialize)]
 pub struct SelfUser {
@@ -12,6 +18,112 @@ pub struct SelfUser {
     pub expires_at: i64,
 }
 
+/// Token of user, without its secret part
+#[derive(Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub id: i64,
+    pub ty: UserTokenTy,
+    pub scope: UserTokenScope,
+    pub label: Option<String>,
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub last_used_at: Option<i64>,
+    /// Token is used by this request
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateTokenBody {
+    pub label: String,
+    /// Should be subset of scopes of token used to create it
+    pub scope: UserTokenScope,
+    /// Lifetime in milliseconds, defaults to and is limited by lifetime of
+    /// personal tokens set by server
+    #[serde(default)]
+    pub lifetime: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TokenPath {
+    pub id: i64,
+}
+
+/// Profile visible to everyone
+#[derive(Serialize, Deserialize)]
+pub struct PublicUser {
+    pub id: i64,
+    /// Current username, differs from requested one if it was changed
+    pub username: String,
+    #[serde(flatten)]
+    pub profile: UserProfile,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UsernamePath {
+    pub username: String,
+}
+
+/// Partial update of profile, `Some(None)` (`null`) removes field
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateSelfBody {
+    /// New username, old one keeps resolving to this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub affiliation: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub orcid: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bio: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub avatar: Option<Option<String>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DeleteSelfBody {
+    /// Username of user that receives projects where deleted user is the
+    /// only owner. Such projects are deleted if not set.
+    #[serde(default)]
+    pub transfer_to: Option<String>,
+}
+
+/// Notification preferences of user
+#[derive(Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Events user is notified about. Notifications are sent only to users
+    /// with linked Telegram account.
+    pub enabled: NotificationKinds,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RevokeTokensQuery {
+    /// Don't revoke token used by this request
+    #[serde(default)]
+    pub keep_current: bool,
+}
+
 pub struct GetSelf;
 impl Endpoint for GetSelf {
     type Query = ();
@@ -28,3 +140,165 @@ impl Endpoint for GetSelf {
         format!("{PREFIX}/{}", Self::partial_path())
     }
 }
+
+pub struct UpdateSelf;
+impl Endpoint for UpdateSelf {
+    type Query = ();
+    type Body = UpdateSelfBody;
+    type Response = PublicUser;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Patch
+    }
+    fn partial_path() -> &'static str {
+        "/@self"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Public profile of user, also resolves previous usernames
+pub struct GetUser(pub UsernamePath);
+impl Endpoint for GetUser {
+    type Query = ();
+    type Body = ();
+    type Response = PublicUser;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:username"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}", self.0.username)
+    }
+}
+
+/// Deletes account of user. Projects where user is the only owner are
+/// transferred to [`DeleteSelfBody::transfer_to`] or deleted with their
+/// sources. Source revisions uploaded by user to projects that remain are
+/// kept, their uploader is cleared. Requires session token.
+pub struct DeleteSelf;
+impl Endpoint for DeleteSelf {
+    type Query = ();
+    type Body = DeleteSelfBody;
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/@self"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+pub struct ListTokens;
+impl Endpoint for ListTokens {
+    type Query = ();
+    type Body = ();
+    type Response = Vec<TokenInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/@self/tokens"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Creates [`UserTokenTy::Personal`] token
+pub struct CreateToken;
+impl Endpoint for CreateToken {
+    type Query = ();
+    type Body = CreateTokenBody;
+    type Response = IssueUserTokenResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/@self/tokens"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+pub struct RevokeToken(pub TokenPath);
+impl Endpoint for RevokeToken {
+    type Query = ();
+    type Body = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/@self/tokens/:id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/@self/tokens/{}", self.0.id)
+    }
+}
+
+/// Revokes all tokens of user, including the current one unless
+/// [`RevokeTokensQuery::keep_current`] is set
+pub struct RevokeAllTokens;
+impl Endpoint for RevokeAllTokens {
+    type Query = RevokeTokensQuery;
+    type Body = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/@self/tokens"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+pub struct GetNotificationSettings;
+impl Endpoint for GetNotificationSettings {
+    type Query = ();
+    type Body = ();
+    type Response = NotificationSettings;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/@self/notifications"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
+
+/// Replaces notification preferences, unknown kinds are ignored
+pub struct UpdateNotificationSettings;
+impl Endpoint for UpdateNotificationSettings {
+    type Query = ();
+    type Body = NotificationSettings;
+    type Response = NotificationSettings;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/@self/notifications"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}{}", Self::partial_path())
+    }
+}
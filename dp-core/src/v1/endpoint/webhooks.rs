@@ -0,0 +1,199 @@
+//! Webhooks of projects, managed by owners. See [`crate::v1::webhook`] for
+//! requests sent to them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::v1::webhook::{DeliveryStatus, WebhookEvents};
+
+use super::{projects::ProjectPath, Endpoint, HTTPMethod};
+
+/// Webhooks are nested in projects
+pub use super::projects::PREFIX;
+
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPath {
+    pub id: i64,
+    pub webhook_id: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DeliveryPath {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub delivery_id: i64,
+}
+
+/// Webhook without its secret
+#[derive(Serialize, Deserialize)]
+pub struct WebhookInfo {
+    pub id: i64,
+    pub project_id: i64,
+    pub url: String,
+    pub events: WebhookEvents,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateWebhookBody {
+    /// `http` or `https` URL
+    pub url: String,
+    pub events: WebhookEvents,
+    /// Secret of signatures, generated by server if not set
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateWebhookResponse {
+    #[serde(flatten)]
+    pub webhook: WebhookInfo,
+    /// Secret is shown only once
+    pub secret: String,
+}
+
+/// Partial update of webhook, missing fields are not changed
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateWebhookBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<WebhookEvents>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DeliveryListQuery {
+    #[serde(default)]
+    pub limit: u32,
+    #[serde(default)]
+    pub skip: u32,
+}
+
+/// Delivery of event to webhook
+#[derive(Serialize, Deserialize)]
+pub struct DeliveryInfo {
+    pub id: i64,
+    pub webhook_id: i64,
+    /// Type of event, see [`crate::v1::webhook::Event::name`]
+    pub event: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub created_at: i64,
+    pub last_attempt_at: Option<i64>,
+    /// Status of last response
+    pub response_status: Option<i64>,
+    /// Why last attempt failed
+    pub error: Option<String>,
+}
+
+pub struct ListWebhooks(pub ProjectPath);
+impl Endpoint for ListWebhooks {
+    type Body = ();
+    type Query = ();
+    type Response = Vec<WebhookInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/webhooks", self.0.id)
+    }
+}
+
+pub struct CreateWebhook(pub ProjectPath);
+impl Endpoint for CreateWebhook {
+    type Body = CreateWebhookBody;
+    type Query = ();
+    type Response = CreateWebhookResponse;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Put
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/webhooks", self.0.id)
+    }
+}
+
+pub struct UpdateWebhook(pub WebhookPath);
+impl Endpoint for UpdateWebhook {
+    type Body = UpdateWebhookBody;
+    type Query = ();
+    type Response = WebhookInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Patch
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks/:webhook_id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/webhooks/{}", self.0.id, self.0.webhook_id)
+    }
+}
+
+/// Deletes webhook with its deliveries, pending ones are not sent
+pub struct DeleteWebhook(pub WebhookPath);
+impl Endpoint for DeleteWebhook {
+    type Body = ();
+    type Query = ();
+    type Response = ();
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Delete
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks/:webhook_id"
+    }
+    fn build_path(&self) -> String {
+        format!("{PREFIX}/{}/webhooks/{}", self.0.id, self.0.webhook_id)
+    }
+}
+
+/// Lists deliveries of webhook, newest first
+pub struct ListDeliveries(pub WebhookPath);
+impl Endpoint for ListDeliveries {
+    type Body = ();
+    type Query = DeliveryListQuery;
+    type Response = Vec<DeliveryInfo>;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Get
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks/:webhook_id/deliveries"
+    }
+    fn build_path(&self) -> String {
+        format!(
+            "{PREFIX}/{}/webhooks/{}/deliveries",
+            self.0.id, self.0.webhook_id
+        )
+    }
+}
+
+/// Sends payload of delivery again as new delivery
+pub struct Redeliver(pub DeliveryPath);
+impl Endpoint for Redeliver {
+    type Body = ();
+    type Query = ();
+    type Response = DeliveryInfo;
+
+    fn method() -> HTTPMethod {
+        HTTPMethod::Post
+    }
+    fn partial_path() -> &'static str {
+        "/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver"
+    }
+    fn build_path(&self) -> String {
+        format!(
+            "{PREFIX}/{}/webhooks/{}/deliveries/{}/redeliver",
+            self.0.id, self.0.webhook_id, self.0.delivery_id
+        )
+    }
+}
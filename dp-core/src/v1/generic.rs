@@ -34,3 +34,14 @@ macro_rules! define_types {
 }
 
 pub(crate) use define_types;
+
+/// Deserializes present field (even `null`) as `Some`. Used with
+/// `#[serde(default)]` to tell apart missing field and `null` in partial
+/// updates.
+pub(crate) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
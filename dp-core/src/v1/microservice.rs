@@ -0,0 +1,136 @@
+//! Signing of requests made by microservices
+//!
+//! Microservice signs every request with one of its keys and sends
+//! ```text
+//! Authorization: DP-HMAC-SHA256 service=<name>,timestamp=<ms>,nonce=<nonce>,signature=<hex>
+//! ```
+//! Signature is HMAC-SHA256 of [`canonical_request`]. Timestamp should be
+//! close to server time and nonce can't be reused, so captured requests
+//! can't be replayed.
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Authorization scheme of signed requests
+pub const SCHEME: &str = "DP-HMAC-SHA256";
+
+/// Name of Telegram bot service
+pub const TELEGRAM: &str = "telegram";
+
+type SignatureMac = Hmac<Sha256>;
+
+/// Hex SHA-256 of request body
+pub fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// String that is signed. `path` includes query string if request has it.
+pub fn canonical_request(
+    method: &str,
+    path: &str,
+    body_hash: &str,
+    timestamp: i64,
+    nonce: &str,
+) -> String {
+    format!(
+        "{}\n{path}\n{body_hash}\n{timestamp}\n{nonce}",
+        method.to_ascii_uppercase()
+    )
+}
+
+fn signature_mac(key: &str, canonical: &str) -> SignatureMac {
+    let mut mac = SignatureMac::new_from_slice(key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(canonical.as_bytes());
+    mac
+}
+
+/// Hex signature of canonical request
+pub fn sign(key: &str, canonical: &str) -> String {
+    hex::encode(signature_mac(key, canonical).finalize().into_bytes())
+}
+
+/// Checks signature in constant time
+/// # Example
+/// ```
+/// # use dp_core::v1::microservice::{canonical_request, body_hash, sign, verify};
+/// let canonical = canonical_request("GET", "/v1/auth/telegram", &body_hash(b""), 0, "n");
+/// let signature = sign("key", &canonical);
+/// assert!(verify("key", &canonical, &signature));
+/// assert!(!verify("other key", &canonical, &signature));
+/// ```
+pub fn verify(key: &str, canonical: &str, signature: &str) -> bool {
+    hex::decode(signature).is_ok_and(|v| signature_mac(key, canonical).verify_slice(&v).is_ok())
+}
+
+/// Parameters of `Authorization` header of signed request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub service: String,
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+impl Signature {
+    /// Signs request
+    pub fn new(
+        service: &str,
+        key: &str,
+        method: &str,
+        path: &str,
+        body: &[u8],
+        timestamp: i64,
+        nonce: &str,
+    ) -> Self {
+        let canonical = canonical_request(method, path, &body_hash(body), timestamp, nonce);
+        Self {
+            service: service.to_owned(),
+            timestamp,
+            nonce: nonce.to_owned(),
+            signature: sign(key, &canonical),
+        }
+    }
+
+    /// Parses value of `Authorization` header
+    /// # Example
+    /// ```
+    /// # use dp_core::v1::microservice::Signature;
+    /// let v = Signature::new("telegram", "key", "PUT", "/v1/auth/telegram", b"", 1, "abc");
+    /// assert_eq!(Signature::parse(&v.to_string()), Some(v));
+    /// assert_eq!(Signature::parse("Bearer 1:token"), None);
+    /// ```
+    pub fn parse(header: &str) -> Option<Self> {
+        let params = header.strip_prefix(SCHEME)?.strip_prefix(' ')?;
+
+        let (mut service, mut timestamp, mut nonce, mut signature) = (None, None, None, None);
+        for param in params.split(',') {
+            let (k, v) = param.trim().split_once('=')?;
+            match k {
+                "service" => service = Some(v.to_owned()),
+                "timestamp" => timestamp = v.parse().ok(),
+                "nonce" => nonce = Some(v.to_owned()),
+                "signature" => signature = Some(v.to_owned()),
+                _ => return None,
+            }
+        }
+
+        Some(Self {
+            service: service?,
+            timestamp: timestamp?,
+            nonce: nonce?,
+            signature: signature?,
+        })
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{SCHEME} service={},timestamp={},nonce={},signature={}",
+            self.service, self.timestamp, self.nonce, self.signature
+        )
+    }
+}
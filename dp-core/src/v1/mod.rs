@@ -4,5 +4,8 @@ mod generic;
 
 pub mod api;
 pub mod endpoint;
+pub mod microservice;
+pub mod notification;
 pub mod project;
 pub mod user;
+pub mod webhook;
@@ -0,0 +1,87 @@
+//! Notifications pushed to users through Telegram bot
+
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+use crate::v1::project::{BuildStatus, ProjectRole};
+
+/// Event user is notified about
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Build of source revision finished
+    BuildFinished {
+        project_id: i64,
+        project_title: String,
+        source_id: i64,
+        status: BuildStatus,
+    },
+    /// User was added to project
+    MemberAdded {
+        project_id: i64,
+        project_title: String,
+        role: ProjectRole,
+        /// Username of owner that added user
+        added_by: String,
+    },
+    /// Invite created by user was used to register
+    InviteClaimed { invite_id: i64, username: String },
+    /// Registration of user was approved or rejected by moderator
+    ModerationDecision { approved: bool },
+}
+
+impl Event {
+    /// Returns kind of event, user is notified only if it is enabled
+    /// # Example
+    /// ```
+    /// # use dp_core::v1::notification::{Event, NotificationKinds};
+    /// let event = Event::ModerationDecision { approved: true };
+    /// assert!(event.kind() == NotificationKinds::MODERATION);
+    /// assert!(NotificationKinds::DEFAULT.contains(event.kind()));
+    /// ```
+    pub fn kind(&self) -> NotificationKinds {
+        match self {
+            Self::BuildFinished {
+                status: BuildStatus::Succeeded,
+                ..
+            } => NotificationKinds::BUILD_SUCCEEDED,
+            Self::BuildFinished { .. } => NotificationKinds::BUILD_FAILED,
+            Self::MemberAdded { .. } => NotificationKinds::MEMBER_ADDED,
+            Self::InviteClaimed { .. } => NotificationKinds::INVITE_CLAIMED,
+            Self::ModerationDecision { .. } => NotificationKinds::MODERATION,
+        }
+    }
+}
+
+/// Kinds of events user wants to be notified about
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub struct NotificationKinds(i64);
+
+bitflags! {
+    impl NotificationKinds: i64 {
+        const BUILD_SUCCEEDED = 1 << 0;
+        const BUILD_FAILED = 1 << 1;
+        /// User was added to project
+        const MEMBER_ADDED = 1 << 2;
+        /// Invite of user was claimed
+        const INVITE_CLAIMED = 1 << 3;
+        /// Registration of user was approved or rejected
+        const MODERATION = 1 << 4;
+
+        /// Enabled for new users
+        const DEFAULT = Self::BUILD_SUCCEEDED.bits()
+            | Self::BUILD_FAILED.bits()
+            | Self::MEMBER_ADDED.bits()
+            | Self::INVITE_CLAIMED.bits()
+            | Self::MODERATION.bits();
+    }
+}
+
+/// Body of signed request sent to Telegram bot
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TelegramNotification {
+    /// Telegram user (and private chat) that receives notification
+    pub telegram_id: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
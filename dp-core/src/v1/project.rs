@@ -1,11 +1,159 @@
+use std::path::{Component, Path};
+
 use serde::{Deserialize, Serialize};
 
 use crate::v1::generic::define_types;
 
 define_types! {
     /// Type of project
-    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default)]
     pub enum ProjectTy: i64 {
+        /// Project built with default command of server
         Legacy = 0,
+        #[default]
+        Latex = 1,
+        Typst = 2,
+        /// Markdown or other format supported by Pandoc
+        Markdown = 3,
+        /// Already built PDF
+        Pdf = 4,
+    }
+}
+
+define_types! {
+    /// How sources of project are turned into PDF
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+    pub enum BuildRecipe: i64 {
+        /// Default compiler command of server
+        Default = 0,
+        Latexmk = 1,
+        Typst = 2,
+        Pandoc = 3,
+        /// Entrypoint is PDF itself and copied as is
+        Copy = 4,
+    }
+}
+
+impl ProjectTy {
+    /// File that passed to compiler if project does not specify other.
+    /// Returns `None` for [`ProjectTy::Legacy`], its entrypoint is set by
+    /// server.
+    pub const fn default_entrypoint(self) -> Option<&'static str> {
+        match self {
+            Self::Legacy => None,
+            Self::Latex => Some("main.tex"),
+            Self::Typst => Some("main.typ"),
+            Self::Markdown => Some("main.md"),
+            Self::Pdf => Some("paper.pdf"),
+        }
+    }
+
+    /// Recipe used to build project
+    pub const fn recipe(self) -> BuildRecipe {
+        match self {
+            Self::Legacy => BuildRecipe::Default,
+            Self::Latex => BuildRecipe::Latexmk,
+            Self::Typst => BuildRecipe::Typst,
+            Self::Markdown => BuildRecipe::Pandoc,
+            Self::Pdf => BuildRecipe::Copy,
+        }
+    }
+}
+
+/// Checks if project title is correct
+/// # Example
+/// ```
+/// # use dp_core::v1::project::check_project_title;
+/// assert_eq!(check_project_title("On the Electrodynamics of Moving Bodies"), true);
+/// assert_eq!(check_project_title("A"), false);
+/// ```
+pub fn check_project_title(v: &str) -> bool {
+    matches!(v.len(), 2..=40)
+}
+
+/// Checks if entrypoint is relative path inside of sources
+/// # Example
+/// ```
+/// # use dp_core::v1::project::check_entrypoint;
+/// assert_eq!(check_entrypoint("chapters/main.tex"), true);
+/// assert_eq!(check_entrypoint("../main.tex"), false);
+/// assert_eq!(check_entrypoint("/etc/passwd"), false);
+/// ```
+pub fn check_entrypoint(v: &str) -> bool {
+    !v.is_empty()
+        && v.len() <= 255
+        && Path::new(v)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+define_types! {
+    /// Format of uploaded source archive
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+    pub enum SourceFormat: i64 {
+        Tar = 0,
+        TarGz = 1,
+        Zip = 2,
+    }
+}
+
+impl SourceFormat {
+    /// File extension of archive
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+            Self::Zip => "zip",
+        }
+    }
+
+    /// MIME type of archive
+    pub const fn mime(self) -> &'static str {
+        match self {
+            Self::Tar => "application/x-tar",
+            Self::TarGz => "application/gzip",
+            Self::Zip => "application/zip",
+        }
+    }
+}
+
+define_types! {
+    /// Status of source revision build
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+    pub enum BuildStatus: i64 {
+        Queued = 0,
+        Running = 1,
+        Succeeded = 2,
+        Failed = 3,
+        /// Uploaded before builds were introduced, built only on request
+        NotBuilt = 4,
+    }
+}
+
+define_types! {
+    /// Who can see project and its PDF
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default)]
+    pub enum ProjectVisibility: i64 {
+        /// Only members of project
+        #[default]
+        Private = 0,
+        /// Anyone who knows link, but not listed
+        Unlisted = 1,
+        /// Anyone, listed in public projects
+        Public = 2,
+    }
+}
+
+define_types! {
+    /// Role of member in project. Every role includes permissions of
+    /// previous ones.
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum ProjectRole: i64 {
+        /// Can see project, its sources and builds
+        Viewer = 0,
+        /// Can also edit project, upload sources and start builds
+        Editor = 1,
+        /// Can also manage members, change visibility and delete project
+        Owner = 2,
     }
 }
@@ -3,7 +3,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::v1::generic::define_types;
+use crate::v1::{api, generic::define_types};
 
 define_types! {
     /// Type of user
@@ -20,32 +20,127 @@ define_types! {
     }
 }
 
+define_types! {
+    /// Role of user on server
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+    pub enum UserRole: i64 {
+        #[default]
+        User = 0,
+        /// Can approve and reject users that are not [`UserTy::Normal`]
+        Moderator = 1,
+        /// Can manage users and projects with `/admin` endpoints
+        Admin = 2,
+    }
+}
+
+impl UserRole {
+    /// Returns scopes available to tokens of user with role
+    pub const fn max_scope(self) -> UserTokenScope {
+        match self {
+            Self::User => UserTokenScope::all()
+                .difference(UserTokenScope::ADMIN)
+                .difference(UserTokenScope::MODERATE),
+            Self::Moderator => UserTokenScope::all().difference(UserTokenScope::ADMIN),
+            Self::Admin => UserTokenScope::all(),
+        }
+    }
+}
+
+impl UserTy {
+    /// Returns scopes available to tokens of user. Users that are not
+    /// approved yet have read-only access.
+    pub const fn max_scope(self) -> UserTokenScope {
+        match self {
+            Self::Normal => UserTokenScope::all(),
+            Self::Unregistered | Self::Unverified => UserTokenScope::READ_PROFILE
+                .union(UserTokenScope::READ_PROJECTS)
+                .union(UserTokenScope::MANAGE_TOKENS),
+        }
+    }
+}
+
 define_types! {
     /// Type of user token
     #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
     pub enum UserTokenTy: i64 {
         UserLimited = 0,
         TelegramAuthorization = 1,
+        /// Named token created by user, e.g. for CI
+        Personal = 2,
+        /// Short-lived token issued together with refresh token
+        Access = 3,
     }
 }
 
 impl UserTokenTy {
-    /// Returns how long token lives in milliseconds.
-    pub const fn lifetime(self) -> i64 {
+    /// Returns scopes of newly issued token.
+    pub const fn default_scope(self) -> UserTokenScope {
         match self {
-            Self::UserLimited => 999999999,
-            Self::TelegramAuthorization => 20 * 60 * 1000,
+            Self::UserLimited => UserTokenScope::DEFAULT,
+            Self::TelegramAuthorization => UserTokenScope::READ_PROFILE,
+            Self::Personal => UserTokenScope::empty(),
+            Self::Access => UserTokenScope::DEFAULT,
         }
     }
 }
 
 /// Scopes of user token
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub struct UserTokenScope(i64);
 
 bitflags! {
     impl UserTokenScope: i64 {
-        const _ = !0;
+        /// Read profile of token owner
+        const READ_PROFILE = 1 << 0;
+        /// Read projects, their sources, builds and PDFs
+        const READ_PROJECTS = 1 << 1;
+        /// Create, edit and delete projects and manage their members
+        const WRITE_PROJECTS = 1 << 2;
+        const UPLOAD_SOURCES = 1 << 3;
+        const TRIGGER_BUILDS = 1 << 4;
+        /// Issue and revoke tokens of owner
+        const MANAGE_TOKENS = 1 << 5;
+        /// Administration of server, requires [`UserRole::Admin`]. Never
+        /// granted by default, tokens with it are issued with server CLI.
+        const ADMIN = 1 << 6;
+        /// Issue and revoke invites
+        const MANAGE_INVITES = 1 << 7;
+        /// Approve and reject users, requires [`UserRole::Moderator`]
+        const MODERATE = 1 << 8;
+        /// Edit profile and username of token owner
+        const WRITE_PROFILE = 1 << 9;
+
+        /// Scopes of token issued on login
+        const DEFAULT = Self::READ_PROFILE.bits()
+            | Self::READ_PROJECTS.bits()
+            | Self::WRITE_PROJECTS.bits()
+            | Self::UPLOAD_SOURCES.bits()
+            | Self::TRIGGER_BUILDS.bits()
+            | Self::MANAGE_TOKENS.bits()
+            | Self::MANAGE_INVITES.bits()
+            | Self::MODERATE.bits()
+            | Self::WRITE_PROFILE.bits();
+    }
+}
+
+impl UserTokenScope {
+    /// Checks that token has all of `scope`
+    /// # Example
+    /// ```
+    /// # use dp_core::v1::{api, user::UserTokenScope};
+    /// let ci = UserTokenScope::UPLOAD_SOURCES;
+    /// assert!(ci.require(UserTokenScope::UPLOAD_SOURCES).is_ok());
+    /// assert!(matches!(
+    ///     ci.require(UserTokenScope::WRITE_PROJECTS),
+    ///     Err(api::Error::NoAccess)
+    /// ));
+    /// ```
+    pub fn require(self, scope: Self) -> Result<(), api::Error> {
+        if self.contains(scope) {
+            Ok(())
+        } else {
+            Err(api::Error::NoAccess)
+        }
     }
 }
 
@@ -57,6 +152,7 @@ pub struct UserToken {
     pub user_id: i64,
     pub scope: UserTokenScope,
     pub issued_at: i64,
+    pub expires_at: i64,
 
     pub token: String,
 }
@@ -67,7 +163,21 @@ pub struct User {
     pub id: i64,
     pub ty: UserTy,
     pub username: String,
-    pub telegram_id: i64,
+    /// `None` for users that log in with password
+    pub telegram_id: Option<i64>,
+    pub role: UserRole,
+}
+
+/// Public profile of user, all fields are optional
+#[derive(Serialize, Deserialize, Default)]
+pub struct UserProfile {
+    pub display_name: Option<String>,
+    pub affiliation: Option<String>,
+    /// ORCID iD in form `0000-0002-1825-0097`
+    pub orcid: Option<String>,
+    pub bio: Option<String>,
+    /// HTTPS URL of avatar image
+    pub avatar: Option<String>,
 }
 
 /// Checks if user name is correct
@@ -81,3 +191,50 @@ pub fn check_username(v: &str) -> bool {
     static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9.\-]+$").unwrap());
     RE.is_match(v)
 }
+
+/// Checks if password has acceptable length
+/// # Example
+/// ```
+/// # use dp_core::v1::user::check_password;
+/// assert_eq!(check_password("correct horse battery staple"), true);
+/// assert_eq!(check_password("hunter2"), false);
+/// ```
+pub fn check_password(v: &str) -> bool {
+    matches!(v.chars().count(), 8..=128)
+}
+
+/// Checks if ORCID iD is correct, including its check digit
+/// # Example
+/// ```
+/// # use dp_core::v1::user::check_orcid;
+/// assert_eq!(check_orcid("0000-0002-1825-0097"), true);
+/// assert_eq!(check_orcid("0000-0002-1694-233X"), true);
+/// assert_eq!(check_orcid("0000-0002-1825-0098"), false);
+/// assert_eq!(check_orcid("0000000218250097"), false);
+/// ```
+pub fn check_orcid(v: &str) -> bool {
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{4}-\d{4}-\d{4}-\d{3}[\dX]$").unwrap());
+    if !RE.is_match(v) {
+        return false;
+    }
+
+    let digits: Vec<u32> = v.chars().filter_map(|c| c.to_digit(10)).collect();
+    let total = digits[..15].iter().fold(0, |acc, d| (acc + d) * 2);
+    let check = (12 - total % 11) % 11;
+    match v.chars().last() {
+        Some('X') => check == 10,
+        Some(c) => c.to_digit(10) == Some(check),
+        None => false,
+    }
+}
+
+/// Checks if avatar is HTTPS URL of reasonable length
+/// # Example
+/// ```
+/// # use dp_core::v1::user::check_avatar;
+/// assert_eq!(check_avatar("https://example.com/me.png"), true);
+/// assert_eq!(check_avatar("javascript:alert(1)"), false);
+/// ```
+pub fn check_avatar(v: &str) -> bool {
+    v.len() <= 512 && v.strip_prefix("https://").is_some_and(|v| !v.is_empty())
+}
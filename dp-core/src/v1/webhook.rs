@@ -0,0 +1,162 @@
+//! Webhooks of projects
+//!
+//! Server sends [`Payload`] in JSON with `POST` to URL of webhook with
+//! headers
+//! ```text
+//! X-DP-Event: <type of event>
+//! X-DP-Delivery: <id of delivery>
+//! X-DP-Signature: t=<ms>,sha256=<hex>
+//! ```
+//! Signature is HMAC-SHA256 of `<ms>.<body>` with secret of webhook.
+//! Receivers should check it with [`verify`] and ignore deliveries with old
+//! timestamps. Redelivered payloads are the same, but signed again.
+
+use bitflags::bitflags;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::v1::{
+    generic::define_types,
+    project::{BuildStatus, SourceFormat},
+};
+
+pub const EVENT_HEADER: &str = "x-dp-event";
+pub const DELIVERY_HEADER: &str = "x-dp-delivery";
+pub const SIGNATURE_HEADER: &str = "x-dp-signature";
+
+type WebhookMac = Hmac<Sha256>;
+
+/// Event of project
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    SourceUploaded {
+        source_id: i64,
+        format: SourceFormat,
+        size: i64,
+        /// Username of uploader
+        uploaded_by: String,
+    },
+    /// Build of source revision succeeded or failed
+    BuildFinished {
+        source_id: i64,
+        status: BuildStatus,
+        exit_code: Option<i64>,
+    },
+    /// Visibility of project was changed to
+    /// [`ProjectVisibility::Public`](crate::v1::project::ProjectVisibility::Public)
+    ProjectPublished {
+        title: String,
+    },
+    ProjectDeleted,
+}
+
+impl Event {
+    /// Returns kind of event, it is sent only to webhooks subscribed to it
+    /// # Example
+    /// ```
+    /// # use dp_core::v1::webhook::{Event, WebhookEvents};
+    /// let event = Event::ProjectDeleted;
+    /// assert!(event.kind() == WebhookEvents::PROJECT_DELETED);
+    /// assert_eq!(event.name(), "project_deleted");
+    /// ```
+    pub fn kind(&self) -> WebhookEvents {
+        match self {
+            Self::SourceUploaded { .. } => WebhookEvents::SOURCE_UPLOADED,
+            Self::BuildFinished { .. } => WebhookEvents::BUILD_FINISHED,
+            Self::ProjectPublished { .. } => WebhookEvents::PROJECT_PUBLISHED,
+            Self::ProjectDeleted => WebhookEvents::PROJECT_DELETED,
+        }
+    }
+
+    /// Type of event, sent in `X-DP-Event` header
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::SourceUploaded { .. } => "source_uploaded",
+            Self::BuildFinished { .. } => "build_finished",
+            Self::ProjectPublished { .. } => "project_published",
+            Self::ProjectDeleted => "project_deleted",
+        }
+    }
+}
+
+/// Kinds of events webhook is subscribed to
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub struct WebhookEvents(i64);
+
+bitflags! {
+    impl WebhookEvents: i64 {
+        const SOURCE_UPLOADED = 1 << 0;
+        const BUILD_FINISHED = 1 << 1;
+        const PROJECT_PUBLISHED = 1 << 2;
+        const PROJECT_DELETED = 1 << 3;
+    }
+}
+
+/// Body of webhook request
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Payload {
+    pub project_id: i64,
+    /// When event happened, UNIX time in milliseconds
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+define_types! {
+    /// Status of webhook delivery
+    #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+    pub enum DeliveryStatus: i64 {
+        /// Waits for first attempt or retry
+        Pending = 0,
+        /// Receiver responded with 2xx status
+        Succeeded = 1,
+        /// All attempts failed
+        Failed = 2,
+    }
+}
+
+fn signature_mac(secret: &str, timestamp: i64, body: &[u8]) -> WebhookMac {
+    let mut mac = WebhookMac::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key");
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Returns value of `X-DP-Signature` header
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let signature = hex::encode(
+        signature_mac(secret, timestamp, body)
+            .finalize()
+            .into_bytes(),
+    );
+    format!("t={timestamp},sha256={signature}")
+}
+
+/// Checks `X-DP-Signature` header in constant time, returns its timestamp
+/// if signature is valid
+/// # Example
+/// ```
+/// # use dp_core::v1::webhook::{sign, verify};
+/// let header = sign("secret", 1000, b"{}");
+/// assert_eq!(verify("secret", &header, b"{}"), Some(1000));
+/// assert_eq!(verify("secret", &header, b"[]"), None);
+/// assert_eq!(verify("other secret", &header, b"{}"), None);
+/// ```
+pub fn verify(secret: &str, header: &str, body: &[u8]) -> Option<i64> {
+    let (mut timestamp, mut signature) = (None, None);
+    for param in header.split(',') {
+        match param.trim().split_once('=')? {
+            ("t", v) => timestamp = v.parse().ok(),
+            ("sha256", v) => signature = hex::decode(v).ok(),
+            _ => (),
+        }
+    }
+
+    let timestamp = timestamp?;
+    signature_mac(secret, timestamp, body)
+        .verify_slice(&signature?)
+        .ok()
+        .map(|_| timestamp)
+}
@@ -0,0 +1,139 @@
+//! Client of dp-web API
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use dp_core::v1::{
+    api,
+    endpoint::{Endpoint, HTTPMethod},
+    microservice::Signature,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{header::AUTHORIZATION, Method};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::config::Config;
+
+pub enum Error {
+    Http(reqwest::Error),
+    Api(api::Error, Option<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "request failed: {e}"),
+            Self::Api(e, Some(message)) => write!(f, "{}: {message}", e.error_name()),
+            Self::Api(e, None) => write!(f, "{}", e.error_name()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Http(value)
+    }
+}
+
+/// Response of server, see `IntoResponse` of [`api::Response`]
+#[derive(Deserialize)]
+struct Envelope {
+    ok: bool,
+    #[serde(default)]
+    result: serde_json::Value,
+    error_code: Option<u64>,
+    error_message: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    config: &'static Config,
+}
+
+impl Client {
+    pub fn new(config: &'static Config) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// Sends request signed by bot, see [`Signature`]
+    pub async fn request<E>(
+        &self,
+        endpoint: &E,
+        query: &E::Query,
+        body: &E::Body,
+    ) -> Result<E::Response, Error>
+    where
+        E: Endpoint,
+        E::Query: Serialize,
+        E::Body: Serialize,
+        E::Response: DeserializeOwned,
+    {
+        let mut path = format!("/v1{}", endpoint.build_path());
+        if !serde_json::to_value(query).is_ok_and(|v| v.is_null()) {
+            let query = serde_urlencoded::to_string(query)
+                .map_err(|e| Error::Api(api::Error::InvalidInput, Some(e.to_string())))?;
+            if !query.is_empty() {
+                path = format!("{path}?{query}");
+            }
+        }
+        let body = match serde_json::to_value(body) {
+            Ok(serde_json::Value::Null) => Vec::new(),
+            _ => serde_json::to_vec(body).expect("serialize body"),
+        };
+
+        let method = match E::method() {
+            HTTPMethod::Get => Method::GET,
+            HTTPMethod::Post => Method::POST,
+            HTTPMethod::Put => Method::PUT,
+            HTTPMethod::Patch => Method::PATCH,
+            HTTPMethod::Delete => Method::DELETE,
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_millis() as i64;
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let signature = Signature::new(
+            &self.config.service,
+            &self.config.keys[0],
+            method.as_str(),
+            &path,
+            &body,
+            timestamp,
+            &nonce,
+        );
+
+        let mut req = self
+            .http
+            .request(
+                method,
+                format!("{}{path}", self.config.server_url.trim_end_matches('/')),
+            )
+            .header(AUTHORIZATION, signature.to_string());
+        if !body.is_empty() {
+            req = req.header("content-type", "application/json").body(body);
+        }
+
+        let res: Envelope = req.send().await?.json().await?;
+        if res.ok {
+            serde_json::from_value(res.result)
+                .map_err(|e| Error::Api(api::Error::Internal, Some(e.to_string())))
+        } else {
+            let error = res
+                .error_code
+                .and_then(api::Error::from_code)
+                .unwrap_or(api::Error::Internal);
+            Err(Error::Api(error, res.error_message))
+        }
+    }
+}
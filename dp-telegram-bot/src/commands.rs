@@ -0,0 +1,155 @@
+//! Commands sent to bot by users
+
+use dp_core::v1::{
+    api,
+    endpoint::{
+        auth::{
+            ClaimInviteBody, ClaimInviteTelegram, IssueUserTokenQuery, IssueUserTokenResponse,
+            TelegramIssueToken,
+        },
+        projects::{ListTelegramProjects, TelegramProjectListQuery},
+    },
+    user::check_username,
+};
+
+use crate::{
+    client::{Client, Error},
+    config::Config,
+    telegram::{Bot, Message},
+};
+
+const HELP: &str = "Commands:
+/start <invite> - register with invite
+/login - get login link
+/projects - list your projects";
+
+const PRIVATE_ONLY: &str = "Send commands to me in a private chat.";
+
+/// Context of handled message
+pub struct Context {
+    pub bot: Bot,
+    pub client: Client,
+    pub config: &'static Config,
+}
+
+/// Text shown to user when request to server failed
+fn describe(e: &Error) -> String {
+    match e {
+        Error::Api(api::Error::NotFound, _) => {
+            "You are not registered or invite is invalid. Ask for an invite and send /start <invite>."
+                .to_owned()
+        }
+        Error::Api(api::Error::Conflict, _) => {
+            "This Telegram account or username is already registered, use /login.".to_owned()
+        }
+        Error::Api(api::Error::Suspended, _) => "Your account is suspended.".to_owned(),
+        e => {
+            eprintln!("Request to server failed: {e}");
+            "Server is unavailable, try again later.".to_owned()
+        }
+    }
+}
+
+fn login_link(config: &Config, token: &IssueUserTokenResponse) -> String {
+    format!(
+        "Open link to log in, it works once and expires soon:\n{}?token={}:{}",
+        config.login_url, token.user_id, token.token
+    )
+}
+
+/// Username of new user, Telegram username is used if it is valid
+fn username(telegram_id: i64, telegram_username: Option<&str>) -> String {
+    match telegram_username {
+        Some(v) if check_username(v) => v.to_owned(),
+        _ => format!("tg{telegram_id}"),
+    }
+}
+
+async fn issue_token(ctx: &Context, telegram_id: i64) -> Result<IssueUserTokenResponse, Error> {
+    ctx.client
+        .request(
+            &TelegramIssueToken,
+            &IssueUserTokenQuery { telegram_id },
+            &(),
+        )
+        .await
+}
+
+async fn start(
+    ctx: &Context,
+    telegram_id: i64,
+    telegram_username: Option<&str>,
+    invite: &str,
+) -> String {
+    let body = ClaimInviteBody {
+        invite: invite.to_owned(),
+        username: username(telegram_id, telegram_username),
+        telegram_id: Some(telegram_id),
+        password: None,
+    };
+    match ctx.client.request(&ClaimInviteTelegram, &(), &body).await {
+        Ok(token) => format!(
+            "Welcome, {}!\n\n{}",
+            body.username,
+            login_link(ctx.config, &token)
+        ),
+        Err(e) => describe(&e),
+    }
+}
+
+async fn login(ctx: &Context, telegram_id: i64) -> String {
+    match issue_token(ctx, telegram_id).await {
+        Ok(token) => login_link(ctx.config, &token),
+        Err(e) => describe(&e),
+    }
+}
+
+async fn projects(ctx: &Context, telegram_id: i64) -> String {
+    let query = TelegramProjectListQuery {
+        telegram_id,
+        limit: 0,
+        skip: 0,
+    };
+    let list = ctx.client.request(&ListTelegramProjects, &query, &()).await;
+
+    match list {
+        Ok(list) if list.is_empty() => "You have no projects yet.".to_owned(),
+        Ok(list) => list.iter().fold("Your projects:".to_owned(), |acc, v| {
+            format!("{acc}\n#{} {} ({})", v.id, v.title, v.visibility.as_str())
+        }),
+        Err(e) => describe(&e),
+    }
+}
+
+/// Replies to command in message
+pub async fn handle(ctx: &Context, msg: Message) {
+    let (Some(from), Some(text)) = (msg.from, msg.text) else {
+        return;
+    };
+
+    // Replies contain login links and projects of user, members of group
+    // would see them
+    if msg.chat.ty != "private" {
+        if let Err(e) = ctx.bot.send_message(msg.chat.id, PRIVATE_ONLY).await {
+            eprintln!("Failed to send message to {}: {e}", msg.chat.id);
+        }
+        return;
+    }
+
+    let mut args = text.split_whitespace();
+    // Commands in groups are sent as `/command@bot`
+    let command = args
+        .next()
+        .and_then(|v| v.split('@').next())
+        .unwrap_or_default();
+    let reply = match (command, args.next()) {
+        ("/start", Some(invite)) => start(ctx, from.id, from.username.as_deref(), invite).await,
+        ("/login", _) => login(ctx, from.id).await,
+        ("/projects", _) => projects(ctx, from.id).await,
+        _ => HELP.to_owned(),
+    };
+
+    if let Err(e) = ctx.bot.send_message(msg.chat.id, &reply).await {
+        eprintln!("Failed to send message to {}: {e}", msg.chat.id);
+    }
+}
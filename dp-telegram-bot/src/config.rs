@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+#[derive(Clone, Deserialize)]
+pub struct Config {
+    /// Base URL of Telegram Bot API
+    #[serde(default = "default_telegram_api")]
+    pub telegram_api: String,
+
+    pub bot_token: String,
+
+    /// Base URL of dp-web server, without `/v1`
+    pub server_url: String,
+
+    /// Name of bot in `microservices` of server config
+    #[serde(default = "default_service")]
+    pub service: String,
+
+    /// The first key signs requests to server, any of them is accepted in
+    /// notifications
+    pub keys: Vec<String>,
+
+    /// Frontend page that activates login token
+    pub login_url: String,
+
+    /// Address of notification receiver, disabled if not set
+    pub listen: Option<String>,
+}
+
+fn default_telegram_api() -> String {
+    "https://api.telegram.org".to_owned()
+}
+
+fn default_service() -> String {
+    dp_core::v1::microservice::TELEGRAM.to_owned()
+}
@@ -0,0 +1,75 @@
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use clap::Parser;
+
+mod client;
+mod commands;
+mod config;
+mod notify;
+mod telegram;
+
+use client::Client;
+use commands::Context;
+use config::Config;
+use telegram::Bot;
+
+#[derive(Parser)]
+#[command(version, about = "Telegram bot of dev-papers", long_about = None)]
+struct Args {
+    /// Path to config file
+    #[arg(short, long, default_value = "config.yml")]
+    config: PathBuf,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    let config: Config = match fs::read_to_string(&args.config).map(|v| serde_yaml::from_str(&v)) {
+        Ok(Ok(v)) => v,
+        Ok(Err(e)) => panic!(
+            "Failed to parse yaml config file '{}': {e}",
+            args.config.to_string_lossy()
+        ),
+        Err(e) => panic!(
+            "Failed to read config file '{}': {e}",
+            args.config.to_string_lossy()
+        ),
+    };
+    if config.keys.is_empty() {
+        panic!("At least one key should be set");
+    }
+    let config: &'static Config = Box::leak(Box::new(config));
+
+    let bot = Bot::new(config);
+    if let Some(listen) = &config.listen {
+        tokio::spawn(notify::serve(listen, bot.clone(), config));
+    }
+
+    let ctx = Arc::new(Context {
+        bot: bot.clone(),
+        client: Client::new(config),
+        config,
+    });
+
+    println!("Bot started");
+    let mut offset = 0;
+    loop {
+        let updates = match bot.get_updates(offset).await {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Failed to get updates: {e}");
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = offset.max(update.update_id + 1);
+            if let Some(msg) = update.message {
+                let ctx = ctx.clone();
+                tokio::spawn(async move { commands::handle(&ctx, msg).await });
+            }
+        }
+    }
+}
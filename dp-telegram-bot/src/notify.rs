@@ -0,0 +1,172 @@
+//! Receiver of notifications pushed by server
+//!
+//! Server sends [`TelegramNotification`] to `POST /notify`, signed the same
+//! way as requests of bot to server.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode, Uri},
+    routing::post,
+    Router,
+};
+use dp_core::v1::{
+    microservice::{self, Signature},
+    notification::{Event, TelegramNotification},
+    project::BuildStatus,
+};
+use tokio::sync::Mutex;
+
+use crate::{config::Config, telegram::Bot};
+
+/// Maximum difference between timestamp of notification and local time, in
+/// milliseconds
+const MAX_CLOCK_SKEW: i64 = 5 * 60 * 1000;
+
+#[derive(Clone)]
+struct NotifyState {
+    bot: Bot,
+    config: &'static Config,
+    /// Nonces of accepted notifications with their expiration time
+    nonces: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+fn timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as i64
+}
+
+fn message(event: &Event) -> String {
+    match event {
+        Event::BuildFinished {
+            project_id,
+            project_title,
+            source_id,
+            status,
+        } => match status {
+            BuildStatus::Succeeded => {
+                format!("Build of revision {source_id} of #{project_id} {project_title} succeeded")
+            }
+            BuildStatus::Failed => {
+                format!("Build of revision {source_id} of #{project_id} {project_title} failed")
+            }
+            status => format!(
+                "Build of revision {source_id} of #{project_id} {project_title} is {}",
+                status.as_str()
+            ),
+        },
+        Event::MemberAdded {
+            project_id,
+            project_title,
+            role,
+            added_by,
+        } => format!(
+            "{added_by} added you to #{project_id} {project_title} as {}",
+            role.as_str()
+        ),
+        Event::InviteClaimed { username, .. } => {
+            format!("{username} registered with your invite")
+        }
+        Event::ModerationDecision { approved: true } => {
+            "Your registration was approved, use /login to log in.".to_owned()
+        }
+        Event::ModerationDecision { approved: false } => {
+            "Your registration was rejected.".to_owned()
+        }
+    }
+}
+
+/// Checks signature, timestamp and nonce of notification
+async fn verify(state: &NotifyState, headers: &HeaderMap, uri: &Uri, body: &[u8]) -> bool {
+    let Some(signature) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Signature::parse)
+    else {
+        return false;
+    };
+
+    let now = timestamp();
+    if signature.service != state.config.service
+        || (now - signature.timestamp).abs() > MAX_CLOCK_SKEW
+    {
+        return false;
+    }
+
+    let path = uri.path_and_query().map(|v| v.as_str()).unwrap_or("/");
+    let canonical = microservice::canonical_request(
+        "POST",
+        path,
+        &microservice::body_hash(body),
+        signature.timestamp,
+        &signature.nonce,
+    );
+    if !state
+        .config
+        .keys
+        .iter()
+        .any(|key| microservice::verify(key, &canonical, &signature.signature))
+    {
+        return false;
+    }
+
+    let mut nonces = state.nonces.lock().await;
+    nonces.retain(|_, expires_at| *expires_at >= now);
+    nonces
+        .insert(signature.nonce, signature.timestamp + MAX_CLOCK_SKEW)
+        .is_none()
+}
+
+async fn notify(
+    State(state): State<NotifyState>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> StatusCode {
+    if !verify(&state, &headers, &uri, &body).await {
+        return StatusCode::UNAUTHORIZED;
+    }
+    let Ok(notification) = serde_json::from_slice::<TelegramNotification>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+
+    // Private chat with user has the same id as user
+    match state
+        .bot
+        .send_message(notification.telegram_id, &message(&notification.event))
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            eprintln!(
+                "Failed to send notification to {}: {e}",
+                notification.telegram_id
+            );
+            StatusCode::BAD_GATEWAY
+        }
+    }
+}
+
+/// Serves notification receiver at `listen`
+pub async fn serve(listen: &str, bot: Bot, config: &'static Config) {
+    let state = NotifyState {
+        bot,
+        config,
+        nonces: Arc::default(),
+    };
+    let app = Router::new()
+        .route("/notify", post(notify))
+        .with_state(state);
+
+    println!("Notification receiver starting at {listen}");
+    let listener = tokio::net::TcpListener::bind(listen).await.unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
@@ -0,0 +1,121 @@
+//! Minimal client of Telegram Bot API
+
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// Timeout of long polling in seconds
+const POLL_TIMEOUT: u64 = 30;
+
+#[derive(Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+pub struct Message {
+    pub from: Option<User>,
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub username: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Chat {
+    pub id: i64,
+    /// `private`, `group`, `supergroup` or `channel`
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GetUpdates {
+    offset: i64,
+    timeout: u64,
+    allowed_updates: &'static [&'static str],
+}
+
+#[derive(Serialize)]
+struct SendMessage<'a> {
+    chat_id: i64,
+    text: &'a str,
+}
+
+#[derive(Clone)]
+pub struct Bot {
+    http: reqwest::Client,
+    base: String,
+}
+
+impl Bot {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(POLL_TIMEOUT * 2))
+                .build()
+                .expect("build HTTP client"),
+            base: format!(
+                "{}/bot{}",
+                config.telegram_api.trim_end_matches('/'),
+                config.bot_token
+            ),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &impl Serialize,
+    ) -> Result<T, String> {
+        let res: ApiResponse<T> = self
+            .http
+            .post(format!("{}/{method}", self.base))
+            .json(params)
+            .send()
+            .await
+            .and_then(|v| v.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match res.result {
+            Some(v) if res.ok => Ok(v),
+            _ => Err(res
+                .description
+                .unwrap_or_else(|| format!("{method} failed"))),
+        }
+    }
+
+    /// Waits for updates after `offset`
+    pub async fn get_updates(&self, offset: i64) -> Result<Vec<Update>, String> {
+        let params = GetUpdates {
+            offset,
+            timeout: POLL_TIMEOUT,
+            allowed_updates: &["message"],
+        };
+        self.call("getUpdates", &params).await
+    }
+
+    pub async fn send_message(&self, chat_id: i64, text: &str) -> Result<(), String> {
+        let params = SendMessage { chat_id, text };
+        self.call::<serde_json::Value>("sendMessage", &params)
+            .await
+            .map(|_| ())
+    }
+}
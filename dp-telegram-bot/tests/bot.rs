@@ -0,0 +1,358 @@
+//! Bot running against fake Telegram Bot API and stub of dp-web server
+
+use std::{
+    net::TcpListener as StdTcpListener,
+    process::{Child, Command},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, Method, StatusCode, Uri},
+    routing::{get, post, put},
+    Json, Router,
+};
+use dp_core::v1::{
+    api,
+    endpoint::{
+        auth::{ClaimInviteBody, IssueUserTokenQuery, IssueUserTokenResponse},
+        projects::{ProjectInfo, TelegramProjectListQuery},
+    },
+    microservice::{self, Signature},
+    notification::{Event, TelegramNotification},
+    project::{BuildStatus, ProjectTy, ProjectVisibility},
+    user::UserTokenTy,
+};
+use serde_json::{json, Value};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, Mutex},
+};
+
+const KEY: &str = "bot-test-key";
+const TELEGRAM_ID: i64 = 42;
+/// Group where user is a member
+const GROUP_ID: i64 = -1001;
+
+fn timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Fake Telegram Bot API, updates are queued by test and sent messages are
+/// passed back to it
+#[derive(Clone)]
+struct FakeTelegram {
+    updates: Arc<Mutex<Vec<Value>>>,
+    sent: mpsc::UnboundedSender<(i64, String)>,
+}
+
+async fn get_updates(State(fake): State<FakeTelegram>, Json(params): Json<Value>) -> Json<Value> {
+    let offset = params["offset"].as_i64().unwrap_or_default();
+    let updates: Vec<Value> = fake
+        .updates
+        .lock()
+        .await
+        .iter()
+        .filter(|v| v["update_id"].as_i64().unwrap() >= offset)
+        .cloned()
+        .collect();
+    if updates.is_empty() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Json(json!({ "ok": true, "result": updates }))
+}
+
+async fn send_message(State(fake): State<FakeTelegram>, Json(params): Json<Value>) -> Json<Value> {
+    let chat_id = params["chat_id"].as_i64().unwrap();
+    let text = params["text"].as_str().unwrap().to_owned();
+    fake.sent.send((chat_id, text)).unwrap();
+    Json(json!({ "ok": true, "result": {} }))
+}
+
+fn signed(method: &Method, uri: &Uri, headers: &HeaderMap, body: &[u8]) -> bool {
+    let Some(signature) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Signature::parse)
+    else {
+        return false;
+    };
+    let canonical = microservice::canonical_request(
+        method.as_str(),
+        uri.path_and_query().unwrap().as_str(),
+        &microservice::body_hash(body),
+        signature.timestamp,
+        &signature.nonce,
+    );
+    signature.service == microservice::TELEGRAM
+        && microservice::verify(KEY, &canonical, &signature.signature)
+}
+
+fn success(result: impl serde::Serialize) -> Json<Value> {
+    Json(json!({ "ok": true, "result": result }))
+}
+
+fn error(error: api::Error) -> Json<Value> {
+    Json(json!({ "ok": false, "error_code": error as u32 }))
+}
+
+fn token(user_id: i64) -> IssueUserTokenResponse {
+    IssueUserTokenResponse {
+        issued_at: timestamp(),
+        expires_in: 1200,
+        user_id,
+        token: "login-token".to_owned(),
+        ty: UserTokenTy::TelegramAuthorization,
+        refresh_token: None,
+        refresh_expires_at: None,
+    }
+}
+
+async fn claim_invite(method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Json<Value> {
+    if !signed(&method, &uri, &headers, &body) {
+        return error(api::Error::AuthorizationRequired);
+    }
+    let body: ClaimInviteBody = serde_json::from_slice(&body).unwrap();
+    match body.invite.as_str() {
+        "good-invite" if body.telegram_id == Some(TELEGRAM_ID) => success(token(7)),
+        _ => error(api::Error::NotFound),
+    }
+}
+
+async fn issue_token(
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<IssueUserTokenQuery>,
+) -> Json<Value> {
+    if !signed(&method, &uri, &headers, b"") {
+        return error(api::Error::AuthorizationRequired);
+    }
+    match query.telegram_id {
+        TELEGRAM_ID => success(token(7)),
+        _ => error(api::Error::NotFound),
+    }
+}
+
+async fn list_projects(
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<TelegramProjectListQuery>,
+) -> Json<Value> {
+    if !signed(&method, &uri, &headers, b"") {
+        return error(api::Error::AuthorizationRequired);
+    }
+    if query.telegram_id != TELEGRAM_ID {
+        return error(api::Error::NotFound);
+    }
+    success([ProjectInfo {
+        id: 3,
+        ty: ProjectTy::default(),
+        title: "Paper".to_owned(),
+        description: None,
+        author_id: 7,
+        entrypoint: None,
+        visibility: ProjectVisibility::Public,
+    }])
+}
+
+/// Kills bot when test ends
+struct Bot(Child);
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+struct Harness {
+    fake: FakeTelegram,
+    sent: mpsc::UnboundedReceiver<(i64, String)>,
+    next_update: i64,
+    notify_url: String,
+    _bot: Bot,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let (sent_tx, sent) = mpsc::unbounded_channel();
+        let fake = FakeTelegram {
+            updates: Arc::default(),
+            sent: sent_tx,
+        };
+        let telegram_api = serve(
+            Router::new()
+                .route("/bottest/getUpdates", post(get_updates))
+                .route("/bottest/sendMessage", post(send_message))
+                .with_state(fake.clone()),
+        )
+        .await;
+        let server_url = serve(
+            Router::new()
+                .route("/v1/auth/telegram/invite", post(claim_invite))
+                .route("/v1/auth/telegram", put(issue_token))
+                .route("/v1/projects/telegram", get(list_projects)),
+        )
+        .await;
+
+        let listen = {
+            let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let config = std::env::temp_dir().join(format!("dp-bot-test-{}.yml", std::process::id()));
+        std::fs::write(
+            &config,
+            format!(
+                r#"
+telegram_api: {telegram_api}
+bot_token: test
+server_url: {server_url}
+keys: [{KEY}]
+login_url: https://papers.example.org/login/telegram
+listen: {listen}
+"#
+            ),
+        )
+        .unwrap();
+        let bot = Command::new(env!("CARGO_BIN_EXE_dp-telegram-bot"))
+            .arg("-c")
+            .arg(&config)
+            .spawn()
+            .unwrap();
+
+        Self {
+            fake,
+            sent,
+            next_update: 1,
+            notify_url: format!("http://{listen}/notify"),
+            _bot: Bot(bot),
+        }
+    }
+
+    /// Sends message to bot in private chat, returns its reply
+    async fn command(&mut self, text: &str) -> String {
+        self.command_in(json!({ "id": TELEGRAM_ID, "type": "private" }), text)
+            .await;
+        self.reply().await
+    }
+
+    /// Sends message to bot in chat, reply is read by caller
+    async fn command_in(&mut self, chat: Value, text: &str) {
+        let update = json!({
+            "update_id": self.next_update,
+            "message": {
+                "from": { "id": TELEGRAM_ID, "username": "alice" },
+                "chat": chat,
+                "text": text,
+            },
+        });
+        self.next_update += 1;
+        self.fake.updates.lock().await.push(update);
+    }
+
+    /// Returns next message sent by bot to user
+    async fn reply(&mut self) -> String {
+        let (chat_id, text) = self.sent_message().await;
+        assert_eq!(chat_id, TELEGRAM_ID);
+        text
+    }
+
+    async fn sent_message(&mut self) -> (i64, String) {
+        tokio::time::timeout(Duration::from_secs(10), self.sent.recv())
+            .await
+            .expect("bot replied")
+            .unwrap()
+    }
+
+    /// Pushes notification to bot, returns status of response
+    async fn notify(&self, body: &TelegramNotification, key: &str) -> StatusCode {
+        let body = serde_json::to_vec(body).unwrap();
+        let signature = Signature::new(
+            microservice::TELEGRAM,
+            key,
+            "POST",
+            "/notify",
+            &body,
+            timestamp(),
+            &format!("nonce-{}", timestamp()),
+        );
+        let client = reqwest::Client::new();
+        // Receiver starts together with bot
+        for _ in 0..50 {
+            let res = client
+                .post(&self.notify_url)
+                .header(AUTHORIZATION, signature.to_string())
+                .header("content-type", "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            match res {
+                Ok(v) => return StatusCode::from_u16(v.status().as_u16()).unwrap(),
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+        panic!("notification receiver is not started");
+    }
+}
+
+#[tokio::test]
+async fn bot_commands_and_notifications() {
+    let mut bot = Harness::start().await;
+
+    let reply = bot.command("/start wrong-invite").await;
+    assert!(reply.contains("invite is invalid"), "{reply}");
+
+    let reply = bot.command("/start good-invite").await;
+    assert!(reply.starts_with("Welcome, alice!"), "{reply}");
+    assert!(reply.ends_with("?token=7:login-token"), "{reply}");
+
+    let reply = bot.command("/login").await;
+    assert!(reply.ends_with("?token=7:login-token"), "{reply}");
+
+    // Login links are not sent to groups
+    bot.command_in(
+        json!({ "id": GROUP_ID, "type": "supergroup" }),
+        "/login@bot",
+    )
+    .await;
+    let (chat_id, reply) = bot.sent_message().await;
+    assert_eq!(chat_id, GROUP_ID);
+    assert!(!reply.contains("token="), "{reply}");
+
+    let reply = bot.command("/projects").await;
+    assert_eq!(reply, "Your projects:\n#3 Paper (Public)");
+
+    let reply = bot.command("/unknown").await;
+    assert!(reply.starts_with("Commands:"), "{reply}");
+
+    let notification = TelegramNotification {
+        telegram_id: TELEGRAM_ID,
+        event: Event::BuildFinished {
+            project_id: 3,
+            project_title: "Paper".to_owned(),
+            source_id: 5,
+            status: BuildStatus::Succeeded,
+        },
+    };
+    assert_eq!(
+        bot.notify(&notification, "wrong-key").await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(bot.notify(&notification, KEY).await, StatusCode::NO_CONTENT);
+    let reply = bot.reply().await;
+    assert_eq!(reply, "Build of revision 5 of #3 Paper succeeded");
+}
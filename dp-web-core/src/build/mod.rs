@@ -0,0 +1,148 @@
+//! Build of uploaded sources into PDF
+//!
+//! Every source revision is extracted into its scratch directory, compiled
+//! by command of project's [`BuildRecipe`] from
+//! [`BuildConfig`](crate::config::BuildConfig) and resulting
+//! PDF is stored next to the archive. Builds are run by workers from
+//! [`queue`].
+
+use std::{fs::File, io, path::Path, process::Stdio, time::Duration};
+
+use dp_core::v1::project::{BuildRecipe, SourceFormat};
+use tokio::io::AsyncWriteExt;
+
+use crate::{config::Config, sources};
+
+pub mod queue;
+pub mod sandbox;
+
+/// Result of compiler run
+pub enum BuildOutcome {
+    Succeeded {
+        exit_code: Option<i32>,
+    },
+    Failed {
+        exit_code: Option<i32>,
+    },
+    /// Compiler was killed after [`BuildConfig::timeout`](crate::config::BuildConfig::timeout)
+    TimedOut,
+}
+
+/// Replaces `{input}` and `{output}` placeholders in compiler command
+fn expand_command(command: &[String], input: &str, output: &str) -> Vec<String> {
+    command
+        .iter()
+        .map(|v| v.replace("{input}", input).replace("{output}", output))
+        .collect()
+}
+
+pub(crate) async fn append_log(path: &Path, message: &str) {
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await;
+    if let Ok(mut file) = file {
+        _ = file.write_all(format!("\n{message}\n").as_bytes()).await;
+    }
+}
+
+/// Builds source revision in its scratch directory. Output of compiler is
+/// written to build log.
+pub async fn build_source(
+    config: &Config,
+    project_id: i64,
+    source_id: i64,
+    format: SourceFormat,
+    recipe: BuildRecipe,
+    entrypoint: &str,
+) -> io::Result<BuildOutcome> {
+    let scratch = sources::scratch_dir(config, project_id, source_id);
+    let log_path = sources::log_path(config, project_id, source_id);
+
+    match tokio::fs::remove_dir_all(&scratch).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    tokio::fs::create_dir_all(&scratch).await?;
+
+    let archive = sources::archive_path(config, project_id, source_id, format);
+    let extracted = {
+        let scratch = scratch.clone();
+        let limits = sources::UnpackLimits::new(config);
+        tokio::task::spawn_blocking(move || {
+            sources::extract_archive(format, &archive, &scratch, limits)
+        })
+        .await
+        .expect("extract source archive")
+    };
+    match extracted {
+        Err(e) if e.kind() == io::ErrorKind::FileTooLarge => {
+            _ = tokio::fs::remove_dir_all(&scratch).await;
+            tokio::fs::write(
+                &log_path,
+                "Unpacked archive is too large or has too many entries\n",
+            )
+            .await?;
+            return Ok(BuildOutcome::Failed { exit_code: None });
+        }
+        res => res?,
+    }
+
+    let log = File::create(&log_path)?;
+    let (output, exit_code) = match config.build.recipe_command(recipe) {
+        Some(command) => {
+            let output = Path::new(entrypoint).with_extension("pdf");
+            let output = output.to_string_lossy().into_owned();
+
+            let mut child = sandbox::command(
+                &config.build.sandbox,
+                Path::new(&config.papers_path),
+                &scratch,
+                &expand_command(command, entrypoint, &output),
+            )?
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()?;
+
+            let timeout = Duration::from_secs(config.build.timeout);
+            let status = match tokio::time::timeout(timeout, child.wait()).await {
+                Ok(status) => status?,
+                Err(_) => {
+                    sandbox::kill(&mut child);
+                    _ = child.wait().await;
+                    _ = tokio::fs::remove_dir_all(&scratch).await;
+                    return Ok(BuildOutcome::TimedOut);
+                }
+            };
+
+            if !status.success() {
+                _ = tokio::fs::remove_dir_all(&scratch).await;
+                return Ok(BuildOutcome::Failed {
+                    exit_code: status.code(),
+                });
+            }
+            (output, status.code())
+        }
+        None => (entrypoint.to_owned(), None),
+    };
+
+    let outcome = match tokio::fs::copy(
+        scratch.join(&output),
+        sources::pdf_path(config, project_id, source_id),
+    )
+    .await
+    {
+        Ok(_) => BuildOutcome::Succeeded { exit_code },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            append_log(&log_path, &format!("`{output}` was not found in sources")).await;
+            BuildOutcome::Failed { exit_code }
+        }
+        Err(e) => return Err(e),
+    };
+
+    _ = tokio::fs::remove_dir_all(&scratch).await;
+
+    Ok(outcome)
+}
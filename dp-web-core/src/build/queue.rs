@@ -0,0 +1,317 @@
+//! Persistent queue of builds
+//!
+//! Jobs are stored in `build_job` table, so queued builds survive restarts.
+//! Workers are woken up by [`BuildQueue::push`] and also poll the table
+//! for retried jobs.
+
+use std::{sync::Arc, time::Duration};
+
+use dp_core::v1::{
+    notification::Event,
+    project::{BuildStatus, ProjectRole, ProjectTy, SourceFormat},
+    webhook,
+};
+use sqlx::{Pool, Sqlite};
+use tokio::{sync::Notify, task::JoinHandle};
+
+use crate::{config::Config, notifications, routes::AppState, sources, timestamp, webhooks};
+
+use super::{append_log, build_source, BuildOutcome};
+
+/// How often workers look for delayed jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Handle of build queue used to wake up workers
+#[derive(Clone, Default)]
+pub struct BuildQueue {
+    notify: Arc<Notify>,
+}
+
+struct Job {
+    id: i64,
+    source_id: i64,
+    attempts: i64,
+}
+
+impl BuildQueue {
+    /// Marks source revision as queued and adds build job for it.
+    pub async fn push(&self, db: &Pool<Sqlite>, source_id: i64) {
+        let status = BuildStatus::Queued as i64;
+        sqlx::query!(
+            "update project_source set build_status = ?, build_started_at = null, build_finished_at = null, build_exit_code = null where id = ?",
+            status,
+            source_id
+        )
+        .execute(db)
+        .await
+        .expect("queue source build");
+
+        let created_at = timestamp();
+        sqlx::query!(
+            "insert into build_job(source_id,status,created_at,run_after) values (?,?,?,?)",
+            source_id,
+            status,
+            created_at,
+            created_at
+        )
+        .execute(db)
+        .await
+        .expect("insert build job");
+
+        self.notify.notify_one();
+    }
+}
+
+/// Marks jobs that were running while server stopped as failed and queues
+/// sources that are marked as queued but have no job.
+pub async fn recover(db: &Pool<Sqlite>) {
+    let (running, failed) = (BuildStatus::Running as i64, BuildStatus::Failed as i64);
+    let queued = BuildStatus::Queued as i64;
+    let now = timestamp();
+
+    sqlx::query!(
+        "update build_job set status = ?, finished_at = ? where status = ?",
+        failed,
+        now,
+        running
+    )
+    .execute(db)
+    .await
+    .expect("fail orphaned build jobs");
+    sqlx::query!(
+        "update project_source set build_status = ?, build_finished_at = ? where build_status = ?",
+        failed,
+        now,
+        running
+    )
+    .execute(db)
+    .await
+    .expect("fail orphaned builds");
+
+    sqlx::query!(
+        r#"insert into build_job(source_id,status,created_at,run_after)
+            select id, ?, ?, ? from project_source
+            where build_status = ? and id not in (select source_id from build_job where status = ?)"#,
+        queued,
+        now,
+        now,
+        queued,
+        queued
+    )
+    .execute(db)
+    .await
+    .expect("queue lost builds");
+}
+
+/// Spawns build workers. Number of workers is set by
+/// [`BuildConfig::workers`](crate::config::BuildConfig::workers).
+pub fn spawn_workers(state: &AppState) -> Vec<JoinHandle<()>> {
+    (0..state.config.build.workers.max(1))
+        .map(|_| tokio::spawn(worker(state.clone())))
+        .collect()
+}
+
+async fn worker(state: AppState) {
+    loop {
+        match claim(&state.db).await {
+            Some(job) => process(&state, job).await,
+            None => {
+                tokio::select! {
+                    _ = state.builds.notify.notified() => (),
+                    _ = tokio::time::sleep(POLL_INTERVAL) => (),
+                }
+            }
+        }
+    }
+}
+
+/// Takes the oldest ready job and marks it as running
+async fn claim(db: &Pool<Sqlite>) -> Option<Job> {
+    let (queued, running) = (BuildStatus::Queued as i64, BuildStatus::Running as i64);
+    let now = timestamp();
+
+    sqlx::query!(
+        r#"update build_job set status = ?, started_at = ?, attempts = attempts + 1
+            where id = (select id from build_job where status = ? and run_after <= ? order by id limit 1)
+            returning id, source_id, attempts"#,
+        running,
+        now,
+        queued,
+        now
+    )
+    .fetch_optional(db)
+    .await
+    .expect("claim build job")
+    .map(|v| Job {
+        id: v.id,
+        source_id: v.source_id,
+        attempts: v.attempts,
+    })
+}
+
+async fn process(AppState { config, db, .. }: &AppState, job: Job) {
+    let source = sqlx::query!(
+        r#"select project_source.project_id, project_source.format, project.ty, project.entrypoint
+            from project_source
+            join project on project_source.project_id = project.id
+            where project_source.id = ?"#,
+        job.source_id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select project source");
+    let Some(source) = source else {
+        finish_job(db, job.id, BuildStatus::Failed).await;
+        return;
+    };
+
+    let running = BuildStatus::Running as i64;
+    let started_at = timestamp();
+    sqlx::query!(
+        "update project_source set build_status = ?, build_started_at = ? where id = ?",
+        running,
+        started_at,
+        job.source_id
+    )
+    .execute(db)
+    .await
+    .expect("start source build");
+
+    let format = SourceFormat::from_bits(source.format);
+    let ty = ProjectTy::from_bits(source.ty);
+    let entrypoint = source
+        .entrypoint
+        .as_deref()
+        .or(ty.default_entrypoint())
+        .unwrap_or(&config.build.entrypoint);
+    let log_path = sources::log_path(config, source.project_id, job.source_id);
+    let res = build_source(
+        config,
+        source.project_id,
+        job.source_id,
+        format,
+        ty.recipe(),
+        entrypoint,
+    )
+    .await;
+
+    let (status, exit_code) = match res {
+        Ok(BuildOutcome::Succeeded { exit_code }) => (BuildStatus::Succeeded, exit_code),
+        Ok(BuildOutcome::Failed { exit_code }) => (BuildStatus::Failed, exit_code),
+        Ok(BuildOutcome::TimedOut) => {
+            append_log(
+                &log_path,
+                &format!("Build timed out after {} seconds", config.build.timeout),
+            )
+            .await;
+            (BuildStatus::Failed, None)
+        }
+        Err(e) if job.attempts < config.build.max_attempts => {
+            eprintln!(
+                "Failed to build source {} (attempt {}), retrying: {e}",
+                job.source_id, job.attempts
+            );
+            retry_job(db, &job, config.build.retry_delay).await;
+            return;
+        }
+        Err(e) => {
+            eprintln!("Failed to build source {}: {e}", job.source_id);
+            append_log(&log_path, &format!("Build failed: {e}")).await;
+            (BuildStatus::Failed, None)
+        }
+    };
+
+    let istatus = status as i64;
+    let finished_at = timestamp();
+    sqlx::query!(
+        "update project_source set build_status = ?, build_finished_at = ?, build_exit_code = ? where id = ?",
+        istatus,
+        finished_at,
+        exit_code,
+        job.source_id
+    )
+    .execute(db)
+    .await
+    .expect("finish source build");
+
+    finish_job(db, job.id, status).await;
+    notify_members(config, db, source.project_id, job.source_id, status).await;
+
+    let event = webhook::Event::BuildFinished {
+        source_id: job.source_id,
+        status,
+        exit_code: exit_code.map(i64::from),
+    };
+    let mut conn = db.acquire().await.expect("acquire connection");
+    webhooks::enqueue(&mut conn, source.project_id, event).await;
+}
+
+/// Notifies members that can upload sources about finished build
+async fn notify_members(
+    config: &Config,
+    db: &Pool<Sqlite>,
+    project_id: i64,
+    source_id: i64,
+    status: BuildStatus,
+) {
+    let editor = ProjectRole::Editor as i64;
+    let members = sqlx::query!(
+        r#"select project_member.user_id, project.title
+            from project_member
+            join project on project_member.project_id = project.id
+            where project_member.project_id = ? and project_member.role >= ?"#,
+        project_id,
+        editor
+    )
+    .fetch_all(db)
+    .await
+    .expect("select project members");
+
+    for member in members {
+        let event = Event::BuildFinished {
+            project_id,
+            project_title: member.title,
+            source_id,
+            status,
+        };
+        notifications::enqueue(config, db, member.user_id, event).await;
+    }
+}
+
+async fn finish_job(db: &Pool<Sqlite>, id: i64, status: BuildStatus) {
+    let status = status as i64;
+    let finished_at = timestamp();
+    sqlx::query!(
+        "update build_job set status = ?, finished_at = ? where id = ?",
+        status,
+        finished_at,
+        id
+    )
+    .execute(db)
+    .await
+    .expect("finish build job");
+}
+
+async fn retry_job(db: &Pool<Sqlite>, job: &Job, retry_delay: u64) {
+    let queued = BuildStatus::Queued as i64;
+    let delay = (retry_delay * 1000).saturating_mul(1 << (job.attempts - 1).clamp(0, 16)) as i64;
+    let run_after = timestamp() + delay;
+
+    sqlx::query!(
+        "update build_job set status = ?, run_after = ? where id = ?",
+        queued,
+        run_after,
+        job.id
+    )
+    .execute(db)
+    .await
+    .expect("retry build job");
+    sqlx::query!(
+        "update project_source set build_status = ? where id = ?",
+        queued,
+        job.source_id
+    )
+    .execute(db)
+    .await
+    .expect("requeue source build");
+}
@@ -0,0 +1,153 @@
+//! Restricted environment of compiler
+//!
+//! Sources of papers are untrusted, so compiler runs with cleared
+//! environment, TeX shell escape and file access outside of working
+//! directory disabled, resource limits and inside bubblewrap namespaces.
+//! Compiler is run without bubblewrap only if
+//! [`SandboxConfig::unsafe_no_isolation`] is set.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::process::{Child, Command};
+
+use crate::config::SandboxConfig;
+
+/// Environment of every compiler. Lowercase variables override `texmf.cnf`
+/// of TeX distributions.
+const BASE_ENV: &[(&str, &str)] = &[
+    ("LANG", "C.UTF-8"),
+    // Disables `\write18`
+    ("shell_escape", "f"),
+    // Forbids absolute paths, `..` and dot files in `\input` and `\openout`
+    ("openin_any", "p"),
+    ("openout_any", "p"),
+];
+
+fn bwrap_args(sandbox: &SandboxConfig, papers_path: &Path, scratch: &Path) -> Vec<PathBuf> {
+    let mut args: Vec<PathBuf> = ["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]
+        .map(PathBuf::from)
+        .to_vec();
+    args.extend(["--tmpfs".into(), "/tmp".into()]);
+
+    let hidden = std::iter::once(papers_path).chain(sandbox.hide_paths.iter().map(Path::new));
+    for path in hidden {
+        if path.is_dir() {
+            args.extend(["--tmpfs".into(), path.to_owned()]);
+        } else if path.exists() {
+            args.extend(["--ro-bind".into(), "/dev/null".into(), path.to_owned()]);
+        }
+    }
+
+    args.extend(["--bind".into(), scratch.to_owned(), scratch.to_owned()]);
+    args.extend(["--chdir".into(), scratch.to_owned()]);
+    args.extend(["--unshare-all", "--die-with-parent", "--new-session"].map(PathBuf::from));
+
+    args
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn apply_limits(cpu_time: u64, memory: u64, output_size: u64) -> io::Result<()> {
+    fn set(resource: Resource, limit: u64) -> io::Result<()> {
+        if limit == 0 {
+            return Ok(());
+        }
+        let limit = libc::rlimit {
+            rlim_cur: limit as libc::rlim_t,
+            rlim_max: limit as libc::rlim_t,
+        };
+        // SAFETY: pointer to valid `rlimit` is passed
+        match unsafe { libc::setrlimit(resource, &limit) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    // New process group, so whole tree of compiler can be killed
+    // SAFETY: `setsid` is async-signal-safe
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    set(libc::RLIMIT_CPU, cpu_time)?;
+    set(libc::RLIMIT_AS, memory)?;
+    set(libc::RLIMIT_FSIZE, output_size)
+}
+
+/// Creates compiler command that runs in `scratch` directory
+pub fn command(
+    sandbox: &SandboxConfig,
+    papers_path: &Path,
+    scratch: &Path,
+    argv: &[String],
+) -> io::Result<Command> {
+    let Some((program, args)) = argv.split_first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "build command is empty",
+        ));
+    };
+    let scratch = scratch.canonicalize()?;
+
+    let mut command = match &sandbox.bwrap {
+        Some(bwrap) => {
+            let mut command = Command::new(bwrap);
+            command
+                .args(bwrap_args(sandbox, &papers_path.canonicalize()?, &scratch))
+                .arg("--")
+                .arg(program)
+                .args(args);
+            command
+        }
+        None if sandbox.unsafe_no_isolation => {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "compiler sandbox is not configured",
+            ))
+        }
+    };
+
+    command
+        .current_dir(&scratch)
+        .env_clear()
+        .envs(BASE_ENV.iter().copied())
+        .env("PATH", &sandbox.path)
+        .env("HOME", &scratch)
+        .env("TMPDIR", &scratch)
+        .envs(&sandbox.env)
+        .kill_on_drop(true);
+
+    #[cfg(unix)]
+    {
+        let (cpu_time, memory, output_size) =
+            (sandbox.cpu_time, sandbox.memory, sandbox.output_size);
+        // SAFETY: closure only calls async-signal-safe functions
+        unsafe {
+            command.pre_exec(move || apply_limits(cpu_time, memory, output_size));
+        }
+    }
+
+    Ok(command)
+}
+
+/// Kills compiler with all its children
+pub fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: compiler is leader of its own process group
+        unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+    }
+    _ = child.start_kill();
+}
@@ -1,13 +1,417 @@
+use std::collections::HashMap;
+
+use dp_core::v1::{microservice, project::BuildRecipe, user::UserTokenTy};
 use serde::Deserialize;
 
 #[derive(Clone, Deserialize)]
 pub struct Config {
+    /// Telegram bot, see [`TelegramConfig`]
     pub telegram: Option<TelegramConfig>,
 
+    /// Microservices allowed to call internal endpoints, by name
+    #[serde(default)]
+    pub microservices: HashMap<String, MicroserviceConfig>,
+
+    pub oidc: Option<OidcConfig>,
+
     pub papers_path: String,
+
+    /// Secret key of HMAC used to store user tokens. Changing it
+    /// invalidates all issued tokens. Required, server refuses to start
+    /// without it.
+    #[serde(default)]
+    pub token_key: String,
+
+    #[serde(default)]
+    pub token: TokenConfig,
+
+    /// Maximum size of uploaded source archive in bytes
+    #[serde(default = "default_max_source_size")]
+    pub max_source_size: usize,
+
+    /// Maximum total size of files in unpacked source archive in bytes
+    #[serde(default = "default_max_unpacked_size")]
+    pub max_unpacked_size: u64,
+
+    /// Maximum number of entries (files and directories) in source archive
+    #[serde(default = "default_max_archive_entries")]
+    pub max_archive_entries: usize,
+
+    #[serde(default)]
+    pub build: BuildConfig,
+
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+
+    #[serde(default)]
+    pub invites: InviteConfig,
+
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
 }
 
 #[derive(Clone, Deserialize)]
 pub struct TelegramConfig {
-    pub shared_key: String,
+    /// Deprecated, used as key of `telegram` in `microservices`
+    #[serde(default)]
+    pub shared_key: Option<String>,
+
+    /// URL of notification receiver of bot (`POST /notify`), see
+    /// [`crate::notifications`]. Notifications are not sent if not set.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+
+    /// How many times delivery of notification is attempted
+    #[serde(default = "default_notify_max_attempts")]
+    pub max_attempts: i64,
+
+    /// Delay before first retry of delivery in seconds, doubled on every
+    /// next retry
+    #[serde(default = "default_notify_retry_delay")]
+    pub retry_delay: u64,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MicroserviceConfig {
+    /// Keys that requests can be signed with. Several keys are accepted at
+    /// once, so keys can be rotated without downtime.
+    pub keys: Vec<String>,
+}
+
+/// OpenID Connect identity provider, see [`crate::oidc`]
+#[derive(Clone, Deserialize)]
+pub struct OidcConfig {
+    /// Provider metadata is read from
+    /// `<issuer>/.well-known/openid-configuration`
+    pub issuer: String,
+
+    pub client_id: String,
+
+    pub client_secret: String,
+
+    /// Page of frontend that receives `code` and `state` from provider and
+    /// passes them to `/auth/oidc`
+    pub redirect_uri: String,
+
+    #[serde(default = "default_oidc_scope")]
+    pub scope: String,
+}
+
+fn default_oidc_scope() -> String {
+    "openid profile".to_owned()
+}
+
+/// Lifetimes of user tokens in seconds
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct TokenConfig {
+    /// Token issued on login when `refresh` is disabled
+    pub session_lifetime: u64,
+
+    /// Token issued to Telegram bot until user activates it
+    pub telegram_lifetime: u64,
+
+    /// Default and maximum lifetime of personal tokens
+    pub personal_lifetime: u64,
+
+    /// Extend session token by `session_lifetime` on every use
+    pub sliding_expiry: bool,
+
+    /// Issue short-lived access token with refresh token on login instead
+    /// of session token
+    pub refresh: bool,
+
+    pub access_lifetime: u64,
+
+    pub refresh_lifetime: u64,
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            session_lifetime: 999_999,
+            telegram_lifetime: 20 * 60,
+            personal_lifetime: 365 * 24 * 60 * 60,
+            sliding_expiry: false,
+            refresh: false,
+            access_lifetime: 15 * 60,
+            refresh_lifetime: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl TokenConfig {
+    /// Lifetime of token type in milliseconds
+    pub fn lifetime(&self, ty: UserTokenTy) -> i64 {
+        let secs = match ty {
+            UserTokenTy::UserLimited => self.session_lifetime,
+            UserTokenTy::TelegramAuthorization => self.telegram_lifetime,
+            UserTokenTy::Personal => self.personal_lifetime,
+            UserTokenTy::Access => self.access_lifetime,
+        };
+        secs as i64 * 1000
+    }
+}
+
+impl Config {
+    /// Signing keys of microservice
+    pub fn microservice_keys<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> {
+        let legacy = self
+            .telegram
+            .as_ref()
+            .filter(|_| name == microservice::TELEGRAM)
+            .and_then(|v| v.shared_key.as_deref());
+        self.microservices
+            .get(name)
+            .into_iter()
+            .flat_map(|v| v.keys.iter().map(String::as_str))
+            .chain(legacy)
+    }
+}
+
+/// Invites issued by users
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct InviteConfig {
+    /// How many users one user can invite. Unused part of expired and
+    /// revoked invites is not counted.
+    pub quota: i64,
+
+    /// Default and maximum lifetime of invite in seconds
+    pub lifetime: u64,
+}
+
+impl Default for InviteConfig {
+    fn default() -> Self {
+        Self {
+            quota: 5,
+            lifetime: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Delivery of project webhooks, see [`crate::webhooks`]
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// How many times delivery is attempted
+    pub max_attempts: i64,
+
+    /// Delay before first retry in seconds, doubled on every next retry
+    pub retry_delay: u64,
+
+    /// Timeout of single request in seconds
+    pub timeout: u64,
+
+    /// Allow webhooks to loopback, private, link-local, multicast and other
+    /// special-purpose addresses. Owners of projects can reach internal services of server
+    /// with them, so it should be set only on trusted instances.
+    pub allow_private: bool,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            retry_delay: 30,
+            timeout: 10,
+            allow_private: false,
+        }
+    }
+}
+
+/// Garbage collection, see [`crate::maintenance`]
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Interval between runs in seconds, `0` disables periodic runs
+    pub interval: u64,
+
+    /// Unclaimed invites older than this (in seconds) are removed, `0`
+    /// keeps them forever
+    pub invite_ttl: u64,
+
+    /// Finished webhook deliveries older than this (in seconds) are
+    /// removed, `0` keeps them forever
+    pub delivery_ttl: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval: 60 * 60,
+            invite_ttl: 7 * 24 * 60 * 60,
+            delivery_ttl: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct BuildConfig {
+    /// Compiler command of [`BuildRecipe::Default`] (legacy projects).
+    /// `{input}` is replaced with entrypoint file and `{output}` with
+    /// expected PDF file name.
+    pub command: Vec<String>,
+
+    /// Entrypoint of legacy projects
+    pub entrypoint: String,
+
+    pub recipes: RecipesConfig,
+
+    /// Number of concurrent builds
+    pub workers: usize,
+
+    /// Maximum duration (wall-clock) of single build in seconds
+    pub timeout: u64,
+
+    /// How many times job is started before it is marked as failed. Only
+    /// failures not caused by compiler (I/O errors) are retried.
+    pub max_attempts: i64,
+
+    /// Delay before first retry in seconds, doubled on every next retry
+    pub retry_delay: u64,
+
+    pub sandbox: SandboxConfig,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            command: ["latexmk", "-pdf", "-interaction=nonstopmode", "{input}"]
+                .map(String::from)
+                .to_vec(),
+            entrypoint: "main.tex".to_owned(),
+            recipes: RecipesConfig::default(),
+            workers: 2,
+            timeout: 5 * 60,
+            max_attempts: 3,
+            retry_delay: 30,
+            sandbox: SandboxConfig::default(),
+        }
+    }
+}
+
+impl BuildConfig {
+    /// Compiler command of recipe, `None` if recipe does not run compiler
+    pub fn recipe_command(&self, recipe: BuildRecipe) -> Option<&[String]> {
+        match recipe {
+            BuildRecipe::Default => Some(&self.command),
+            BuildRecipe::Latexmk => Some(&self.recipes.latexmk),
+            BuildRecipe::Typst => Some(&self.recipes.typst),
+            BuildRecipe::Pandoc => Some(&self.recipes.pandoc),
+            BuildRecipe::Copy => None,
+        }
+    }
+}
+
+/// Compiler commands of build recipes, same placeholders as in
+/// [`BuildConfig::command`] are used.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct RecipesConfig {
+    pub latexmk: Vec<String>,
+    pub typst: Vec<String>,
+    pub pandoc: Vec<String>,
+}
+
+impl Default for RecipesConfig {
+    fn default() -> Self {
+        Self {
+            latexmk: [
+                "latexmk",
+                "-pdf",
+                "-interaction=nonstopmode",
+                "-no-shell-escape",
+                "{input}",
+            ]
+            .map(String::from)
+            .to_vec(),
+            typst: ["typst", "compile", "{input}", "{output}"]
+                .map(String::from)
+                .to_vec(),
+            pandoc: ["pandoc", "{input}", "-o", "{output}"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Restrictions of compiler process. Limits set to `0` are not applied.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Path to `bwrap` (bubblewrap). When set, compiler is run in separate
+    /// namespaces with read-only view of file system, without network and
+    /// with only scratch directory writable. Required unless
+    /// `unsafe_no_isolation` is set.
+    pub bwrap: Option<String>,
+
+    /// Run compiler without `bwrap`. Compiler can then read any file
+    /// readable by server, including database and this config, so it
+    /// should be set only when all users are trusted.
+    pub unsafe_no_isolation: bool,
+
+    /// Paths hidden from compiler when `bwrap` is used. Papers directory is
+    /// always hidden.
+    pub hide_paths: Vec<String>,
+
+    /// CPU time limit in seconds
+    pub cpu_time: u64,
+
+    /// Address space limit in bytes
+    pub memory: u64,
+
+    /// Maximum size of any written file (including log) in bytes
+    pub output_size: u64,
+
+    /// `PATH` of compiler
+    pub path: String,
+
+    /// Additional environment variables of compiler. Environment of server
+    /// is never passed to compiler.
+    pub env: HashMap<String, String>,
+}
+
+impl SandboxConfig {
+    /// Compiler is run in bubblewrap or running it without isolation is
+    /// allowed explicitly
+    pub fn is_configured(&self) -> bool {
+        self.bwrap.is_some() || self.unsafe_no_isolation
+    }
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            bwrap: None,
+            unsafe_no_isolation: false,
+            hide_paths: Vec::new(),
+            cpu_time: 5 * 60,
+            memory: 2 * 1024 * 1024 * 1024,
+            output_size: 256 * 1024 * 1024,
+            path: "/usr/local/bin:/usr/bin:/bin".to_owned(),
+            env: HashMap::new(),
+        }
+    }
+}
+
+const fn default_max_source_size() -> usize {
+    32 * 1024 * 1024
+}
+
+const fn default_max_unpacked_size() -> u64 {
+    256 * 1024 * 1024
+}
+
+const fn default_max_archive_entries() -> usize {
+    10_000
+}
+
+const fn default_notify_max_attempts() -> i64 {
+    8
+}
+
+const fn default_notify_retry_delay() -> u64 {
+    30
 }
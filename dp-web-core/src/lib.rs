@@ -2,13 +2,64 @@
 //!
 //! Core library of all API endpoints (with implementations).
 
-use sqlx::{sqlite::SqliteQueryResult, SqlitePool};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use sqlx::SqlitePool;
+
+pub mod build;
 pub mod config;
+pub mod maintenance;
+pub mod notifications;
+pub mod oidc;
 pub mod routes;
+pub mod sources;
+pub mod webhooks;
+
+/// All migrations in order of applying. Every migration except the first one
+/// should end with `PRAGMA user_version = <number of migration>;`, so
+/// already applied migrations are skipped.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001-initial.sql"),
+    include_str!("migrations/0002-project-source.sql"),
+    include_str!("migrations/0003-build.sql"),
+    include_str!("migrations/0004-build-job.sql"),
+    include_str!("migrations/0005-project-entrypoint.sql"),
+    include_str!("migrations/0006-project-visibility.sql"),
+    include_str!("migrations/0007-project-member.sql"),
+    include_str!("migrations/0008-token-scope.sql"),
+    include_str!("migrations/0009-token-management.sql"),
+    include_str!("migrations/0010-token-hash.sql"),
+    include_str!("migrations/0011-refresh-token.sql"),
+    include_str!("migrations/0012-invite-lifecycle.sql"),
+    include_str!("migrations/0013-user-role.sql"),
+    include_str!("migrations/0014-user-suspension.sql"),
+    include_str!("migrations/0015-suspension-reason.sql"),
+    include_str!("migrations/0016-user-profile.sql"),
+    include_str!("migrations/0017-local-auth.sql"),
+    include_str!("migrations/0018-oidc.sql"),
+    include_str!("migrations/0019-microservice-nonce.sql"),
+    include_str!("migrations/0020-notification.sql"),
+    include_str!("migrations/0021-webhook.sql"),
+    include_str!("migrations/0022-build-not-built.sql"),
+    include_str!("migrations/0023-oidc-pkce.sql"),
+];
+
+/// Current UNIX time in milliseconds
+pub fn timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as i64
+}
+
+pub async fn apply_migrations(db: &SqlitePool) -> Result<(), sqlx::Error> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(db)
+        .await?;
+
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        sqlx::query(migration).execute(db).await?;
+    }
 
-pub async fn apply_migrations(db: &SqlitePool) -> Result<SqliteQueryResult, sqlx::Error> {
-    sqlx::query(concat!(include_str!("migrations/0001-initial.sql"),))
-        .execute(db)
-        .await
+    Ok(())
 }
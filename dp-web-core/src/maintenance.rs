@@ -0,0 +1,218 @@
+//! Periodic cleanup of database and papers directory
+//!
+//! Expired tokens are otherwise removed only when presented, unclaimed
+//! invites live forever and files of deleted projects or sources may be
+//! left after failures.
+
+use std::{fmt, io, path::Path, time::Duration};
+
+use dp_core::v1::{project::BuildStatus, webhook::DeliveryStatus};
+use sqlx::{Pool, Sqlite};
+use tokio::task::JoinHandle;
+
+use crate::{config::Config, routes::AppState, sources, timestamp};
+
+/// Number of removed objects
+#[derive(Default)]
+pub struct GcReport {
+    pub tokens: u64,
+    pub refresh_tokens: u64,
+    /// Nonces of signed microservice requests
+    pub nonces: u64,
+    pub invites: u64,
+    /// Finished webhook deliveries
+    pub deliveries: u64,
+    /// Webhooks of deleted projects
+    pub webhooks: u64,
+    /// Directories of deleted projects
+    pub projects: u64,
+    /// Directories of deleted source revisions
+    pub sources: u64,
+    /// Scratch directories of finished builds
+    pub scratch: u64,
+}
+
+impl fmt::Display for GcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tokens, {} refresh tokens, {} nonces, {} invites, {} webhook deliveries, {} webhooks, {} project directories, {} source directories, {} scratch directories",
+            self.tokens,
+            self.refresh_tokens,
+            self.nonces,
+            self.invites,
+            self.deliveries,
+            self.webhooks,
+            self.projects,
+            self.sources,
+            self.scratch
+        )
+    }
+}
+
+/// Spawns task that runs [`run_gc`] every
+/// [`MaintenanceConfig::interval`](crate::config::MaintenanceConfig::interval).
+/// Returns `None` if periodic runs are disabled.
+pub fn spawn(state: &AppState) -> Option<JoinHandle<()>> {
+    let interval = state.config.maintenance.interval;
+    if interval == 0 {
+        return None;
+    }
+
+    let AppState { config, db, .. } = state.clone();
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            interval.tick().await;
+            match run_gc(config, &db).await {
+                Ok(report) => println!("Garbage collection removed {report}"),
+                Err(e) => eprintln!("Garbage collection failed: {e}"),
+            }
+        }
+    }))
+}
+
+/// Removes expired tokens, unused stale invites, old webhook deliveries and
+/// orphaned files
+pub async fn run_gc(config: &Config, db: &Pool<Sqlite>) -> io::Result<GcReport> {
+    let now = timestamp();
+    let mut report = GcReport {
+        tokens: sqlx::query!("delete from usertoken where expires_at < ?", now)
+            .execute(db)
+            .await
+            .expect("delete expired tokens")
+            .rows_affected(),
+        refresh_tokens: sqlx::query!("delete from refresh_token where expires_at < ?", now)
+            .execute(db)
+            .await
+            .expect("delete expired refresh tokens")
+            .rows_affected(),
+        nonces: sqlx::query!("delete from microservice_nonce where expires_at < ?", now)
+            .execute(db)
+            .await
+            .expect("delete expired nonces")
+            .rows_affected(),
+        ..Default::default()
+    };
+
+    // Invites that were used are kept, users refer to them
+    let issued_before = match config.maintenance.invite_ttl {
+        0 => None,
+        ttl => Some(now - ttl as i64 * 1000),
+    };
+    report.invites = sqlx::query!(
+        r#"delete from userinvite where uses = 0
+            and (issued_at < ? or expires_at < ? or revoked_at is not null)"#,
+        issued_before,
+        now
+    )
+    .execute(db)
+    .await
+    .expect("delete stale invites")
+    .rows_affected();
+
+    // Webhooks of deleted projects are kept until their deliveries finish
+    let pending = DeliveryStatus::Pending as i64;
+    let finished_before = match config.maintenance.delivery_ttl {
+        0 => None,
+        ttl => Some(now - ttl as i64 * 1000),
+    };
+    report.deliveries = sqlx::query!(
+        r#"delete from webhook_delivery where status != ?
+            and (created_at < ? or webhook_id in
+                (select id from webhook where project_id not in (select id from project)))"#,
+        pending,
+        finished_before
+    )
+    .execute(db)
+    .await
+    .expect("delete finished webhook deliveries")
+    .rows_affected();
+    report.webhooks = sqlx::query!(
+        r#"delete from webhook where project_id not in (select id from project)
+            and id not in (select webhook_id from webhook_delivery)"#
+    )
+    .execute(db)
+    .await
+    .expect("delete webhooks of deleted projects")
+    .rows_affected();
+
+    let papers = Path::new(&config.papers_path);
+    if papers.is_dir() {
+        remove_orphaned_files(config, db, papers, &mut report).await?;
+    }
+
+    Ok(report)
+}
+
+/// Returns ids of entries in directory, entries not named by id are skipped
+async fn read_ids(path: &Path) -> io::Result<Vec<i64>> {
+    let mut ids = vec![];
+    let mut dir = tokio::fs::read_dir(path).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let id = entry.file_name().to_str().and_then(|v| v.parse().ok());
+        if let (Some(id), true) = (id, entry.file_type().await?.is_dir()) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+async fn remove_dir(path: &Path) -> io::Result<()> {
+    match tokio::fs::remove_dir_all(path).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+async fn remove_orphaned_files(
+    config: &Config,
+    db: &Pool<Sqlite>,
+    papers: &Path,
+    report: &mut GcReport,
+) -> io::Result<()> {
+    // Scratch directory of running or queued build may be in use
+    let (succeeded, failed) = (BuildStatus::Succeeded as i64, BuildStatus::Failed as i64);
+
+    for project_id in read_ids(papers).await? {
+        let exists = sqlx::query!("select id from project where id = ?", project_id)
+            .fetch_optional(db)
+            .await
+            .expect("select project")
+            .is_some();
+        if !exists {
+            remove_dir(&sources::project_dir(config, project_id)).await?;
+            report.projects += 1;
+            continue;
+        }
+
+        for source_id in read_ids(&sources::project_dir(config, project_id)).await? {
+            let status = sqlx::query!(
+                "select build_status from project_source where id = ? and project_id = ?",
+                source_id,
+                project_id
+            )
+            .fetch_optional(db)
+            .await
+            .expect("select project source")
+            .map(|v| v.build_status);
+
+            match status {
+                None => {
+                    remove_dir(&sources::source_dir(config, project_id, source_id)).await?;
+                    report.sources += 1;
+                }
+                Some(v) if v == succeeded || v == failed => {
+                    let scratch = sources::scratch_dir(config, project_id, source_id);
+                    if scratch.is_dir() {
+                        remove_dir(&scratch).await?;
+                        report.scratch += 1;
+                    }
+                }
+                Some(_) => (),
+            }
+        }
+    }
+
+    Ok(())
+}
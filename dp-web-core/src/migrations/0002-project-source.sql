@@ -0,0 +1,5 @@
+ALTER TABLE project_source ADD COLUMN format INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE project_source ADD COLUMN size INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE project_source ADD COLUMN uploader_id INTEGER DEFAULT NULL REFERENCES user(id);
+
+PRAGMA user_version = 2;
@@ -0,0 +1,6 @@
+ALTER TABLE project_source ADD COLUMN build_status INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE project_source ADD COLUMN build_started_at INTEGER DEFAULT NULL;
+ALTER TABLE project_source ADD COLUMN build_finished_at INTEGER DEFAULT NULL;
+ALTER TABLE project_source ADD COLUMN build_exit_code INTEGER DEFAULT NULL;
+
+PRAGMA user_version = 3;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS build_job (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    source_id INTEGER NOT NULL,
+    status INTEGER NOT NULL DEFAULT 0,
+    attempts INTEGER NOT NULL DEFAULT 0,
+
+    created_at INTEGER NOT NULL,
+    run_after INTEGER NOT NULL,
+    started_at INTEGER DEFAULT NULL,
+    finished_at INTEGER DEFAULT NULL,
+
+    FOREIGN KEY(source_id) REFERENCES project_source(id)
+);
+
+PRAGMA user_version = 4;
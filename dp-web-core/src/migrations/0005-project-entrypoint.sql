@@ -0,0 +1,3 @@
+ALTER TABLE project ADD COLUMN entrypoint TEXT DEFAULT NULL;
+
+PRAGMA user_version = 5;
@@ -0,0 +1,3 @@
+ALTER TABLE project ADD COLUMN visibility INTEGER NOT NULL DEFAULT 0;
+
+PRAGMA user_version = 6;
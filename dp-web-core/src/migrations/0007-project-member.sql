@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS project_member (
+    project_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role INTEGER NOT NULL DEFAULT 0,
+    added_at INTEGER NOT NULL,
+
+    PRIMARY KEY(project_id, user_id),
+    FOREIGN KEY(project_id) REFERENCES project(id),
+    FOREIGN KEY(user_id) REFERENCES user(id)
+);
+
+INSERT OR IGNORE INTO project_member(project_id, user_id, role, added_at)
+    SELECT id, author_id, 2, 0 FROM project;
+
+PRAGMA user_version = 7;
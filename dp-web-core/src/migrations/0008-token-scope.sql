@@ -0,0 +1,6 @@
+-- Scopes of tokens issued before they were introduced, see
+-- `UserTokenTy::default_scope`
+UPDATE usertoken SET scope = 63 WHERE ty = 0;
+UPDATE usertoken SET scope = 1 WHERE ty = 1;
+
+PRAGMA user_version = 8;
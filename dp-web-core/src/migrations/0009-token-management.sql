@@ -0,0 +1,9 @@
+ALTER TABLE usertoken ADD COLUMN label TEXT DEFAULT NULL;
+ALTER TABLE usertoken ADD COLUMN last_used_at INTEGER DEFAULT NULL;
+ALTER TABLE usertoken ADD COLUMN expires_at INTEGER NOT NULL DEFAULT 0;
+
+-- Lifetimes of tokens issued before, see `UserTokenTy::lifetime`
+UPDATE usertoken SET expires_at = issued_at + 999999999 WHERE ty = 0;
+UPDATE usertoken SET expires_at = issued_at + 1200000 WHERE ty = 1;
+
+PRAGMA user_version = 9;
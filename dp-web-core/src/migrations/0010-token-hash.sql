@@ -0,0 +1,8 @@
+-- Tokens are stored as keyed hashes, plaintext tokens can't be converted
+-- without knowing them, so all issued tokens are invalidated
+DELETE FROM usertoken;
+
+ALTER TABLE usertoken ADD COLUMN token_prefix TEXT NOT NULL DEFAULT '';
+CREATE INDEX IF NOT EXISTS usertoken_prefix ON usertoken(user_id, token_prefix);
+
+PRAGMA user_version = 10;
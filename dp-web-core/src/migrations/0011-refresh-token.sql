@@ -0,0 +1,21 @@
+-- Refresh token family that access token was issued by
+ALTER TABLE usertoken ADD COLUMN family INTEGER DEFAULT NULL;
+
+CREATE TABLE IF NOT EXISTS refresh_token (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL,
+    -- All tokens rotated from the same login
+    family INTEGER NOT NULL,
+
+    token_prefix TEXT NOT NULL,
+    token TEXT NOT NULL,
+
+    issued_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    used_at INTEGER DEFAULT NULL,
+
+    FOREIGN KEY(user_id) REFERENCES user(id)
+);
+CREATE INDEX IF NOT EXISTS refresh_token_prefix ON refresh_token(user_id, token_prefix);
+
+PRAGMA user_version = 11;
@@ -0,0 +1,18 @@
+ALTER TABLE userinvite ADD COLUMN expires_at INTEGER DEFAULT NULL;
+ALTER TABLE userinvite ADD COLUMN max_uses INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE userinvite ADD COLUMN uses INTEGER NOT NULL DEFAULT 0;
+-- `NULL` for invites created with CLI
+ALTER TABLE userinvite ADD COLUMN created_by INTEGER DEFAULT NULL REFERENCES user(id);
+ALTER TABLE userinvite ADD COLUMN revoked_at INTEGER DEFAULT NULL;
+
+UPDATE userinvite SET uses = 1 WHERE claimed_user_id IS NOT NULL;
+
+-- Invite used to register, `userinvite.claimed_user_id` is the first user
+-- of multi-use invite only
+ALTER TABLE user ADD COLUMN invite_id INTEGER DEFAULT NULL REFERENCES userinvite(id);
+UPDATE user SET invite_id = (SELECT id FROM userinvite WHERE claimed_user_id = user.id);
+
+-- `UserTokenScope::MANAGE_INVITES` is a part of default scopes
+UPDATE usertoken SET scope = scope | 128 WHERE ty IN (0, 3);
+
+PRAGMA user_version = 12;
@@ -0,0 +1,6 @@
+ALTER TABLE user ADD COLUMN role INTEGER NOT NULL DEFAULT 0;
+
+-- `UserTokenScope::MODERATE` is a part of default scopes
+UPDATE usertoken SET scope = scope | 256 WHERE ty IN (0, 3);
+
+PRAGMA user_version = 13;
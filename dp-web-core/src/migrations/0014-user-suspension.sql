@@ -0,0 +1,3 @@
+ALTER TABLE user ADD COLUMN suspended_at INTEGER DEFAULT NULL;
+
+PRAGMA user_version = 14;
@@ -0,0 +1,3 @@
+ALTER TABLE user ADD COLUMN suspension_reason TEXT DEFAULT NULL;
+
+PRAGMA user_version = 15;
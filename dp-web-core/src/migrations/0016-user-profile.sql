@@ -0,0 +1,20 @@
+ALTER TABLE user ADD COLUMN display_name TEXT DEFAULT NULL;
+ALTER TABLE user ADD COLUMN affiliation TEXT DEFAULT NULL;
+ALTER TABLE user ADD COLUMN orcid TEXT DEFAULT NULL;
+ALTER TABLE user ADD COLUMN bio TEXT DEFAULT NULL;
+ALTER TABLE user ADD COLUMN avatar TEXT DEFAULT NULL;
+
+-- Previous usernames, they stay reserved for their user so old links keep
+-- resolving
+CREATE TABLE IF NOT EXISTS username_history (
+    username TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    changed_at INTEGER NOT NULL,
+
+    FOREIGN KEY(user_id) REFERENCES user(id)
+);
+
+-- `UserTokenScope::WRITE_PROFILE` is a part of default scopes
+UPDATE usertoken SET scope = scope | 512 WHERE ty IN (0, 3);
+
+PRAGMA user_version = 16;
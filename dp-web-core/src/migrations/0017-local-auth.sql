@@ -0,0 +1,30 @@
+-- `telegram_id` becomes optional, so table is rebuilt
+PRAGMA foreign_keys = OFF;
+
+CREATE TABLE user_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    ty INTEGER NOT NULL DEFAULT 0,
+    username TEXT NOT NULL UNIQUE,
+    telegram_id INTEGER DEFAULT NULL UNIQUE,
+    -- Argon2 hash in PHC string format, `NULL` if password is not set
+    password_hash TEXT DEFAULT NULL,
+    invite_id INTEGER DEFAULT NULL REFERENCES userinvite(id),
+    role INTEGER NOT NULL DEFAULT 0,
+    suspended_at INTEGER DEFAULT NULL,
+    suspension_reason TEXT DEFAULT NULL,
+    display_name TEXT DEFAULT NULL,
+    affiliation TEXT DEFAULT NULL,
+    orcid TEXT DEFAULT NULL,
+    bio TEXT DEFAULT NULL,
+    avatar TEXT DEFAULT NULL
+);
+
+INSERT INTO user_new(id, ty, username, telegram_id, invite_id, role, suspended_at, suspension_reason, display_name, affiliation, orcid, bio, avatar)
+    SELECT id, ty, username, telegram_id, invite_id, role, suspended_at, suspension_reason, display_name, affiliation, orcid, bio, avatar FROM user;
+
+DROP TABLE user;
+ALTER TABLE user_new RENAME TO user;
+
+PRAGMA foreign_keys = ON;
+
+PRAGMA user_version = 17;
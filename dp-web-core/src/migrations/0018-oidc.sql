@@ -0,0 +1,19 @@
+-- Accounts of identity provider linked to users
+CREATE TABLE IF NOT EXISTS user_identity (
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+
+    PRIMARY KEY(issuer, subject),
+    FOREIGN KEY(user_id) REFERENCES user(id)
+);
+
+-- Started logins, `invite` is claimed if user signs up
+CREATE TABLE IF NOT EXISTS oidc_state (
+    state TEXT PRIMARY KEY NOT NULL,
+    invite TEXT DEFAULT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+PRAGMA user_version = 18;
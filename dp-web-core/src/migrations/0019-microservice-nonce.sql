@@ -0,0 +1,11 @@
+-- Nonces of signed microservice requests, kept until their timestamp is out
+-- of accepted window
+CREATE TABLE IF NOT EXISTS microservice_nonce (
+    service TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at INTEGER NOT NULL,
+
+    PRIMARY KEY(service, nonce)
+);
+
+PRAGMA user_version = 19;
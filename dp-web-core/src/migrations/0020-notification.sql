@@ -0,0 +1,20 @@
+-- Kinds of events user is notified about, see
+-- `dp_core::v1::notification::NotificationKinds`
+ALTER TABLE user ADD COLUMN notifications INTEGER NOT NULL DEFAULT 31;
+
+-- Notifications waiting for delivery to Telegram bot. Recipient is resolved
+-- when event happens, so notification is delivered even if user is deleted.
+CREATE TABLE IF NOT EXISTS notification (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    telegram_id INTEGER NOT NULL,
+    -- `dp_core::v1::notification::Event` in JSON
+    event TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+
+    created_at INTEGER NOT NULL,
+    run_after INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS notification_run_after ON notification(run_after);
+
+PRAGMA user_version = 20;
@@ -0,0 +1,36 @@
+-- Webhooks of projects. They are kept after project is deleted until
+-- `project_deleted` event is delivered, so `project_id` is not a foreign key.
+CREATE TABLE IF NOT EXISTS webhook (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    project_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    -- `dp_core::v1::webhook::WebhookEvents`
+    events INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS webhook_project ON webhook(project_id);
+
+CREATE TABLE IF NOT EXISTS webhook_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    webhook_id INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    -- `dp_core::v1::webhook::Payload` in JSON
+    payload TEXT NOT NULL,
+    status INTEGER NOT NULL DEFAULT 0,
+    attempts INTEGER NOT NULL DEFAULT 0,
+
+    created_at INTEGER NOT NULL,
+    run_after INTEGER NOT NULL,
+    last_attempt_at INTEGER DEFAULT NULL,
+    response_status INTEGER DEFAULT NULL,
+    error TEXT DEFAULT NULL,
+
+    FOREIGN KEY(webhook_id) REFERENCES webhook(id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_webhook ON webhook_delivery(webhook_id);
+CREATE INDEX IF NOT EXISTS webhook_delivery_pending ON webhook_delivery(status, run_after);
+
+PRAGMA user_version = 21;
@@ -0,0 +1,7 @@
+-- Sources uploaded before builds were introduced got `BuildStatus::Queued`
+-- from default of `build_status` and were rebuilt by recovery of build queue.
+-- Sources that were never queued have no build jobs.
+UPDATE project_source SET build_status = 4
+    WHERE build_status = 0 AND id NOT IN (SELECT source_id FROM build_job);
+
+PRAGMA user_version = 22;
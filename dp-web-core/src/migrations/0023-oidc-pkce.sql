@@ -0,0 +1,7 @@
+-- Started logins keep PKCE verifier and nonce sent to provider. Logins
+-- started before have neither and are dropped.
+DELETE FROM oidc_state;
+ALTER TABLE oidc_state ADD COLUMN nonce TEXT NOT NULL DEFAULT '';
+ALTER TABLE oidc_state ADD COLUMN code_verifier TEXT NOT NULL DEFAULT '';
+
+PRAGMA user_version = 23;
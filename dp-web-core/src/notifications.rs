@@ -0,0 +1,229 @@
+//! Delivery of notifications to users through Telegram bot
+//!
+//! Events are queued with [`enqueue`] for every recipient that has linked
+//! Telegram account and enabled kind of event. Worker started with [`spawn`]
+//! sends them to [`TelegramConfig::callback_url`] as signed
+//! [`TelegramNotification`] and retries failed deliveries with exponential
+//! backoff.
+
+use std::{fmt, time::Duration};
+
+use dp_core::v1::{
+    microservice::{self, Signature},
+    notification::{Event, NotificationKinds, TelegramNotification},
+};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use sqlx::{Pool, Sqlite, SqliteExecutor};
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::{Config, TelegramConfig},
+    routes::{v1::models::user::generate_token, AppState},
+    timestamp,
+};
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("build HTTP client")
+});
+
+/// How often queue is checked for due notifications
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of notifications sent in one round
+const BATCH_SIZE: i64 = 32;
+
+/// Returns Telegram config if notifications are enabled
+fn telegram_config(config: &Config) -> Option<&TelegramConfig> {
+    config
+        .telegram
+        .as_ref()
+        .filter(|v| v.callback_url.is_some())
+}
+
+/// Queues notification of user about `event`. Does nothing if notifications
+/// are disabled on server, user has no Telegram account or disabled kind of
+/// event.
+pub async fn enqueue(config: &Config, db: &Pool<Sqlite>, user_id: i64, event: Event) {
+    if telegram_config(config).is_none() {
+        return;
+    }
+
+    let user = sqlx::query!(
+        "select telegram_id, notifications from user where id = ?",
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select user notifications");
+    let Some(telegram_id) = user
+        .filter(|v| NotificationKinds::from_bits_retain(v.notifications).contains(event.kind()))
+        .and_then(|v| v.telegram_id)
+    else {
+        return;
+    };
+
+    let event = serde_json::to_string(&event).expect("serialize event");
+    let now = timestamp();
+    sqlx::query!(
+        "insert into notification(telegram_id,event,created_at,run_after) values (?,?,?,?)",
+        telegram_id,
+        event,
+        now,
+        now
+    )
+    .execute(db)
+    .await
+    .expect("insert notification");
+}
+
+/// Drops notifications queued for Telegram account, so they are not kept
+/// after its user is deleted
+pub async fn discard(db: impl SqliteExecutor<'_>, telegram_id: i64) {
+    sqlx::query!(
+        "delete from notification where telegram_id = ?",
+        telegram_id
+    )
+    .execute(db)
+    .await
+    .expect("delete queued notifications");
+}
+
+enum Error {
+    Http(reqwest::Error),
+    Rejected(reqwest::StatusCode),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "request failed: {e}"),
+            Self::Rejected(status) => write!(f, "bot responded with {status}"),
+        }
+    }
+}
+
+/// Sends signed notification to bot
+async fn deliver(url: &Url, key: &str, body: Vec<u8>) -> Result<(), Error> {
+    let path = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_owned(),
+    };
+    let signature = Signature::new(
+        microservice::TELEGRAM,
+        key,
+        "POST",
+        &path,
+        &body,
+        timestamp(),
+        &generate_token(),
+    );
+
+    let res = CLIENT
+        .post(url.clone())
+        .header("authorization", signature.to_string())
+        .header("content-type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(Error::Http)?;
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        Err(Error::Rejected(res.status()))
+    }
+}
+
+/// Sends due notifications, returns `true` if there can be more of them
+async fn process(telegram: &TelegramConfig, url: &Url, key: &str, db: &Pool<Sqlite>) -> bool {
+    let now = timestamp();
+    let due = sqlx::query!(
+        "select id, telegram_id, event, attempts from notification where run_after <= ? order by id limit ?",
+        now,
+        BATCH_SIZE
+    )
+    .fetch_all(db)
+    .await
+    .expect("select due notifications");
+
+    let full = due.len() as i64 == BATCH_SIZE;
+    for notification in due {
+        let event: Event = match serde_json::from_str(&notification.event) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Dropping invalid notification {}: {e}", notification.id);
+                delete(db, notification.id).await;
+                continue;
+            }
+        };
+        let body = serde_json::to_vec(&TelegramNotification {
+            telegram_id: notification.telegram_id,
+            event,
+        })
+        .expect("serialize notification");
+
+        let attempts = notification.attempts + 1;
+        match deliver(url, key, body).await {
+            Ok(()) => delete(db, notification.id).await,
+            Err(e) if attempts < telegram.max_attempts => {
+                eprintln!(
+                    "Failed to deliver notification {} (attempt {attempts}), retrying: {e}",
+                    notification.id
+                );
+                let delay = (telegram.retry_delay * 1000)
+                    .saturating_mul(1 << (attempts - 1).clamp(0, 16))
+                    as i64;
+                let run_after = timestamp() + delay;
+                sqlx::query!(
+                    "update notification set attempts = ?, run_after = ? where id = ?",
+                    attempts,
+                    run_after,
+                    notification.id
+                )
+                .execute(db)
+                .await
+                .expect("retry notification");
+            }
+            Err(e) => {
+                eprintln!("Failed to deliver notification {}: {e}", notification.id);
+                delete(db, notification.id).await;
+            }
+        }
+    }
+    full
+}
+
+async fn delete(db: &Pool<Sqlite>, id: i64) {
+    sqlx::query!("delete from notification where id = ?", id)
+        .execute(db)
+        .await
+        .expect("delete notification");
+}
+
+/// Spawns task that delivers queued notifications. Returns `None` if
+/// notifications are disabled.
+/// # Panics
+/// This function panics if callback URL is invalid or Telegram bot has no
+/// signing key.
+pub fn spawn(state: &AppState) -> Option<JoinHandle<()>> {
+    let AppState { config, db, .. } = state.clone();
+    let telegram = telegram_config(config)?;
+    let url = telegram.callback_url.as_deref().expect("callback URL");
+    let url =
+        Url::parse(url).unwrap_or_else(|e| panic!("Invalid Telegram callback URL '{url}': {e}"));
+    let key = config
+        .microservice_keys(microservice::TELEGRAM)
+        .next()
+        .expect("Telegram notifications require signing key of `telegram` microservice");
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            while process(telegram, &url, key, &db).await {}
+        }
+    }))
+}
@@ -0,0 +1,219 @@
+//! OpenID Connect authorization code flow
+//!
+//! Server is a confidential client of identity provider. User is sent to
+//! [`authorization_url`], provider redirects them to frontend with `code`,
+//! which is exchanged for claims of user with [`exchange_code`]. Code is
+//! bound to login with PKCE (`S256`) and ID token with `nonce`.
+//!
+//! Claims are read from userinfo endpoint. ID token is received directly
+//! from token endpoint, so its signature is not checked, only issuer,
+//! audience, nonce and subject.
+//!
+//! [`authorization_url`]: Provider::authorization_url
+//! [`exchange_code`]: Provider::exchange_code
+
+use std::{fmt, sync::Arc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use once_cell::sync::Lazy;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::config::OidcConfig;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+/// Client of identity provider, metadata of provider is fetched on first use
+#[derive(Clone, Default)]
+pub struct Provider {
+    metadata: Arc<OnceCell<ProviderMetadata>>,
+}
+
+/// Secrets of started login, kept by server until provider redirects user
+/// back
+pub struct Login {
+    pub state: String,
+    pub nonce: String,
+    /// PKCE verifier, provider receives only its SHA-256 hash
+    pub code_verifier: String,
+}
+
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Claims of ID token that are checked
+#[derive(Deserialize)]
+struct IdToken {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    #[serde(default)]
+    nonce: Option<String>,
+}
+
+/// Claims of authenticated user
+#[derive(Deserialize)]
+pub struct UserInfo {
+    /// Identifier of user unique within issuer
+    pub sub: String,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// Provider is unreachable or returned unexpected response
+    Provider(String),
+    /// Provider rejected authorization code or ID token was issued for
+    /// another login
+    Rejected,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Provider(e) => write!(f, "identity provider failed: {e}"),
+            Self::Rejected => write!(f, "authorization code rejected"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Provider(value.to_string())
+    }
+}
+
+impl Provider {
+    async fn metadata(&self, config: &OidcConfig) -> Result<&ProviderMetadata, Error> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    config.issuer.trim_end_matches('/')
+                );
+                let metadata: ProviderMetadata = CLIENT
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                if metadata.issuer.trim_end_matches('/') != config.issuer.trim_end_matches('/') {
+                    return Err(Error::Provider(format!(
+                        "issuer mismatch, got {}",
+                        metadata.issuer
+                    )));
+                }
+                Ok(metadata)
+            })
+            .await
+    }
+
+    /// URL of provider's login page, `state` is returned back with code
+    pub async fn authorization_url(
+        &self,
+        config: &OidcConfig,
+        login: &Login,
+    ) -> Result<String, Error> {
+        let metadata = self.metadata(config).await?;
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(&login.code_verifier));
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", &config.client_id),
+                ("redirect_uri", &config.redirect_uri),
+                ("scope", &config.scope),
+                ("state", &login.state),
+                ("nonce", &login.nonce),
+                ("code_challenge", &challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| Error::Provider(e.to_string()))?;
+        Ok(url.into())
+    }
+
+    /// Exchanges authorization code of login for claims of user
+    pub async fn exchange_code(
+        &self,
+        config: &OidcConfig,
+        login: &Login,
+        code: &str,
+    ) -> Result<UserInfo, Error> {
+        let metadata = self.metadata(config).await?;
+        let res = CLIENT
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &config.redirect_uri),
+                ("client_id", &config.client_id),
+                ("client_secret", &config.client_secret),
+                ("code_verifier", &login.code_verifier),
+            ])
+            .send()
+            .await?;
+        if matches!(
+            res.status(),
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED
+        ) {
+            return Err(Error::Rejected);
+        }
+        let token: TokenResponse = res.error_for_status()?.json().await?;
+
+        let id_token = decode_id_token(&token.id_token)?;
+        let audience = match &id_token.aud {
+            Audience::One(v) => v == &config.client_id,
+            Audience::Many(v) => v.contains(&config.client_id),
+        };
+        if id_token.iss != metadata.issuer
+            || !audience
+            || id_token.nonce.as_deref() != Some(login.nonce.as_str())
+        {
+            return Err(Error::Rejected);
+        }
+
+        let info: UserInfo = CLIENT
+            .get(&metadata.userinfo_endpoint)
+            .bearer_auth(token.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if info.sub != id_token.sub {
+            return Err(Error::Provider("userinfo is of another subject".to_owned()));
+        }
+        Ok(info)
+    }
+}
+
+/// Reads claims of ID token, signature is not checked
+fn decode_id_token(token: &str) -> Result<IdToken, Error> {
+    let malformed = || Error::Provider("malformed ID token".to_owned());
+    let payload = token.split('.').nth(1).ok_or_else(malformed)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| malformed())?;
+    serde_json::from_slice(&payload).map_err(|_| malformed())
+}
@@ -1,6 +1,6 @@
 use sqlx::SqlitePool;
 
-use crate::config::Config;
+use crate::{build::queue::BuildQueue, config::Config, oidc};
 
 pub mod v1;
 
@@ -8,4 +8,6 @@ pub mod v1;
 pub struct AppState {
     pub config: &'static Config,
     pub db: SqlitePool,
+    pub builds: BuildQueue,
+    pub oidc: oidc::Provider,
 }
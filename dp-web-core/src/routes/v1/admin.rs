@@ -0,0 +1,315 @@
+use std::path::Path as FsPath;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, patch, post},
+    Json, Router,
+};
+use dp_core::v1::{
+    api,
+    endpoint::{
+        admin::{
+            AdminProjectPath, DeleteAnyProject, DisableUser, EnableUser, GetStats, ListUsers,
+            RevokeUserTokens, Stats, SuspendUserBody, UpdateUser, UpdateUserBody, UserInfo,
+            UserListQuery, UserPath,
+        },
+        Endpoint,
+    },
+    project::BuildStatus,
+    user::{User, UserRole, UserToken, UserTokenScope, UserTy},
+};
+use sqlx::{Pool, Sqlite};
+
+use crate::{routes::AppState, sources, timestamp};
+
+use super::{models::user::AuthorizedUser, projects::purge_project, users::revoke_tokens};
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new()
+        .route(ListUsers::partial_path(), get(list_users))
+        .route(UpdateUser::partial_path(), patch(update_user))
+        .route(DisableUser::partial_path(), post(disable_user))
+        .route(EnableUser::partial_path(), post(enable_user))
+        .route(RevokeUserTokens::partial_path(), delete(revoke_user_tokens))
+        .route(DeleteAnyProject::partial_path(), delete(delete_any_project))
+        .route(GetStats::partial_path(), get(get_stats))
+}
+
+struct UserRow {
+    id: i64,
+    ty: i64,
+    role: i64,
+    username: String,
+    telegram_id: Option<i64>,
+    suspended_at: Option<i64>,
+    suspension_reason: Option<String>,
+}
+
+impl From<UserRow> for UserInfo {
+    fn from(v: UserRow) -> Self {
+        Self {
+            id: v.id,
+            ty: UserTy::from_bits(v.ty),
+            role: UserRole::from_bits(v.role),
+            username: v.username,
+            telegram_id: v.telegram_id,
+            suspended_at: v.suspended_at,
+            suspension_reason: v.suspension_reason,
+        }
+    }
+}
+
+/// Checks that user is administrator and token allows administration
+fn require_admin(user: &User, token: &UserToken) -> Result<(), api::Error> {
+    token.scope.require(UserTokenScope::ADMIN)?;
+    if user.role < UserRole::Admin {
+        return Err(api::Error::Forbidden);
+    }
+    Ok(())
+}
+
+async fn fetch_user(id: i64, db: &Pool<Sqlite>) -> Result<UserInfo, api::Error> {
+    sqlx::query_as!(
+        UserRow,
+        "select id, ty, role, username, telegram_id, suspended_at, suspension_reason from user where id = ?",
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select user")
+    .map(UserInfo::from)
+    .ok_or(api::Error::NotFound)
+}
+
+pub async fn list_users(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Query(UserListQuery {
+        search,
+        limit,
+        skip,
+    }): Query<<ListUsers as Endpoint>::Query>,
+) -> api::Response<<ListUsers as Endpoint>::Response> {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+
+    let limit = match limit {
+        0 => 50,
+        v @ 1..=50 => v,
+        _ => return api::Response::error(api::Error::InvalidInput),
+    };
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
+
+    let list = sqlx::query_as!(
+        UserRow,
+        r#"select id, ty, role, username, telegram_id, suspended_at, suspension_reason from user
+            where ? is null or instr(lower(username), lower(?)) > 0
+            order by id limit ? offset ?"#,
+        search,
+        search,
+        limit,
+        offset
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(UserInfo::from)
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn update_user(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(UserPath { id }): Path<UserPath>,
+    Json(UpdateUserBody { ty, role }): Json<<UpdateUser as Endpoint>::Body>,
+) -> api::Response<<UpdateUser as Endpoint>::Response> {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+    let mut info = match fetch_user(id, &db).await {
+        Ok(v) => v,
+        Err(e) => return api::Response::error(e),
+    };
+
+    if let Some(ty) = ty {
+        info.ty = ty;
+    }
+    if let Some(role) = role {
+        info.role = role;
+    }
+    if id == user.id && info.role != UserRole::Admin {
+        return api::Response::error(api::Error::Conflict);
+    }
+
+    // Checked in the same statement, so concurrent demotions can't remove
+    // all administrators
+    let (ity, irole, admin) = (info.ty as i64, info.role as i64, UserRole::Admin as i64);
+    let updated = sqlx::query!(
+        r#"update user set ty = ?, role = ? where id = ? and (? = ? or role != ? or exists
+            (select 1 from user where role = ? and suspended_at is null and id != ?))"#,
+        ity,
+        irole,
+        id,
+        irole,
+        admin,
+        admin,
+        admin,
+        id
+    )
+    .execute(&db)
+    .await
+    .expect("update user")
+    .rows_affected();
+    if updated == 0 {
+        return api::Response::error(api::Error::Conflict);
+    }
+
+    api::Response::Success(info)
+}
+
+pub async fn disable_user(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(UserPath { id }): Path<UserPath>,
+    Json(SuspendUserBody { reason }): Json<<DisableUser as Endpoint>::Body>,
+) -> api::Response<<DisableUser as Endpoint>::Response> {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+    if id == user.id {
+        return api::Response::error(api::Error::Conflict);
+    }
+    if let Err(e) = fetch_user(id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let suspended_at = timestamp();
+    sqlx::query!(
+        "update user set suspended_at = coalesce(suspended_at, ?), suspension_reason = ? where id = ?",
+        suspended_at,
+        reason,
+        id
+    )
+    .execute(&db)
+    .await
+    .expect("suspend user");
+
+    match fetch_user(id, &db).await {
+        Ok(v) => api::Response::Success(v),
+        Err(e) => api::Response::error(e),
+    }
+}
+
+pub async fn enable_user(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(UserPath { id }): Path<UserPath>,
+) -> api::Response<<EnableUser as Endpoint>::Response> {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+
+    sqlx::query!(
+        "update user set suspended_at = null, suspension_reason = null where id = ?",
+        id
+    )
+    .execute(&db)
+    .await
+    .expect("unsuspend user");
+
+    match fetch_user(id, &db).await {
+        Ok(v) => api::Response::Success(v),
+        Err(e) => api::Response::error(e),
+    }
+}
+
+pub async fn revoke_user_tokens(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(UserPath { id }): Path<UserPath>,
+) -> api::Response {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_user(id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let mut conn = db.acquire().await.expect("acquire connection");
+    revoke_tokens(id, &mut conn).await;
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn delete_any_project(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(AdminProjectPath { id }): Path<AdminProjectPath>,
+) -> api::Response {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+
+    let exists = sqlx::query!("select id from project where id = ?", id)
+        .fetch_optional(&db)
+        .await
+        .expect("select project")
+        .is_some();
+    if !exists {
+        return api::Response::error(api::Error::NotFound);
+    }
+
+    purge_project(id, config, &db).await;
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn get_stats(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+) -> api::Response<<GetStats as Endpoint>::Response> {
+    if let Err(e) = require_admin(&user, &token) {
+        return api::Response::error(e);
+    }
+
+    let normal = UserTy::Normal as i64;
+    let (queued, running) = (BuildStatus::Queued as i64, BuildStatus::Running as i64);
+    let counts = sqlx::query!(
+        r#"select
+            (select count(*) from user) as "users!: i64",
+            (select count(*) from user where ty != ?) as "pending_users!: i64",
+            (select count(*) from project) as "projects!: i64",
+            (select count(*) from project_source) as "sources!: i64",
+            (select count(*) from build_job where status = ?) as "queued_builds!: i64",
+            (select count(*) from build_job where status = ?) as "running_builds!: i64""#,
+        normal,
+        queued,
+        running
+    )
+    .fetch_one(&db)
+    .await
+    .expect("select stats");
+
+    let papers_path = config.papers_path.clone();
+    let disk_usage = tokio::task::spawn_blocking(move || {
+        sources::disk_usage(FsPath::new(&papers_path)).unwrap_or_default()
+    })
+    .await
+    .expect("count disk usage");
+
+    api::Response::Success(Stats {
+        users: counts.users,
+        pending_users: counts.pending_users,
+        projects: counts.projects,
+        sources: counts.sources,
+        queued_builds: counts.queued_builds,
+        running_builds: counts.running_builds,
+        disk_usage,
+    })
+}
@@ -1,16 +1,63 @@
 pub mod microservice {
     use axum::{
         async_trait,
-        extract::{FromRequestParts, State},
-        http::request::Parts,
+        body::Body,
+        extract::{FromRequestParts, OriginalUri, Request, State},
+        http::{header::AUTHORIZATION, request::Parts},
+        middleware::Next,
+        response::{IntoResponse, Response},
     };
-    use dp_core::v1::api;
+    use dp_core::v1::{
+        api,
+        microservice::{self, Signature},
+    };
+
+    use crate::{routes::AppState, timestamp};
+
+    /// Maximum difference between timestamp of signed request and server
+    /// time, in milliseconds
+    const MAX_CLOCK_SKEW: i64 = 5 * 60 * 1000;
 
-    use crate::routes::AppState;
+    /// Maximum size of body of signed request
+    const MAX_SIGNED_BODY: usize = 1024 * 1024;
 
+    /// Hash of body of signed request, set by [`hash_signed_body`]
+    #[derive(Clone)]
+    struct BodyHash(String);
+
+    /// Microservice that signed request
     #[derive(Clone, Debug, PartialEq, Eq)]
-    pub enum MicroserviceAuthorization {
-        Telegram,
+    pub struct MicroserviceAuthorization {
+        pub name: String,
+    }
+
+    impl MicroserviceAuthorization {
+        pub fn is(&self, name: &str) -> bool {
+            self.name == name
+        }
+    }
+
+    /// Middleware that hashes body of signed requests, so it can be checked
+    /// by [`MicroserviceAuthorization`] extractor before body is consumed
+    pub async fn hash_signed_body(req: Request, next: Next) -> Response {
+        let signed = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with(microservice::SCHEME));
+        if !signed {
+            return next.run(req).await;
+        }
+
+        let (mut parts, body) = req.into_parts();
+        let Ok(body) = axum::body::to_bytes(body, MAX_SIGNED_BODY).await else {
+            return api::EmptyResponse::error(api::Error::InvalidInput).into_response();
+        };
+        parts
+            .extensions
+            .insert(BodyHash(microservice::body_hash(&body)));
+
+        next.run(Request::from_parts(parts, Body::from(body))).await
     }
 
     #[async_trait]
@@ -21,34 +68,73 @@ pub mod microservice {
             parts: &mut Parts,
             state: &AppState,
         ) -> Result<Self, Self::Rejection> {
-            let State(AppState { config, .. }) =
+            let State(AppState { config, db, .. }) =
                 State::<AppState>::from_request_parts(parts, state)
                     .await
                     .expect("state should not fail");
+            let unauthorized = || api::EmptyResponse::error(api::Error::AuthorizationRequired);
 
-            let token = parts
+            let signature = parts
                 .headers
-                .get("Authorization")
+                .get(AUTHORIZATION)
                 .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.split_once(' '));
-
-            let Some((microservice, token)) = token else {
-                return Err(api::EmptyResponse::error(api::Error::AuthorizationRequired));
-            };
-
-            match microservice {
-                "Internal-TelegramMicroservice"
-                    if config
-                        .telegram
-                        .as_ref()
-                        .map(|v| v.shared_key == token)
-                        .unwrap_or_default() =>
-                {
-                    Ok(Self::Telegram)
-                }
-
-                _ => Err(api::EmptyResponse::error(api::Error::AuthorizationRequired)),
+                .and_then(Signature::parse)
+                .ok_or_else(unauthorized)?;
+            let BodyHash(body_hash) = parts
+                .extensions
+                .get::<BodyHash>()
+                .cloned()
+                .ok_or_else(unauthorized)?;
+
+            let now = timestamp();
+            if (now - signature.timestamp).abs() > MAX_CLOCK_SKEW
+                || !matches!(signature.nonce.len(), 16..=64)
+            {
+                return Err(unauthorized());
             }
+
+            // Path is signed as it was sent, before nested routers stripped
+            // their prefixes
+            let uri = parts
+                .extensions
+                .get::<OriginalUri>()
+                .map(|v| &v.0)
+                .unwrap_or(&parts.uri);
+            let path = uri.path_and_query().map(|v| v.as_str()).unwrap_or("/");
+            let canonical = microservice::canonical_request(
+                parts.method.as_str(),
+                path,
+                &body_hash,
+                signature.timestamp,
+                &signature.nonce,
+            );
+
+            let valid = config
+                .microservice_keys(&signature.service)
+                .any(|key| microservice::verify(key, &canonical, &signature.signature));
+            if !valid {
+                return Err(unauthorized());
+            }
+
+            let expires_at = signature.timestamp + MAX_CLOCK_SKEW;
+            let fresh = sqlx::query!(
+                "insert or ignore into microservice_nonce(service, nonce, expires_at) values (?,?,?)",
+                signature.service,
+                signature.nonce,
+                expires_at
+            )
+            .execute(&db)
+            .await
+            .expect("insert microservice nonce")
+            .rows_affected()
+                == 1;
+            if !fresh {
+                return Err(unauthorized());
+            }
+
+            Ok(Self {
+                name: signature.service,
+            })
         }
     }
 }
@@ -2,7 +2,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use axum::{
     extract::{Json, Query, State},
-    routing::{post, put},
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    routing::{get, post, put},
     Router,
 };
 use dp_core::v1::{
@@ -10,17 +12,38 @@ use dp_core::v1::{
     endpoint::{
         auth::{
             ClaimInviteBody, ClaimInviteTelegram, ClaimInviteUser, IssueUserTokenQuery,
-            IssueUserTokenResponse, TelegramActivateToken, TelegramIssueToken,
+            IssueUserTokenResponse, OidcAuthorize, OidcAuthorizeQuery, OidcAuthorizeResponse,
+            OidcCallback, OidcCallbackBody, PasswordLogin, PasswordLoginBody, RefreshUserToken,
+            RefreshUserTokenBody, SetPassword, SetPasswordBody, TelegramActivateToken,
+            TelegramIssueToken,
         },
         Endpoint,
     },
-    user::{check_username, User, UserToken, UserTokenTy},
+    microservice,
+    notification::Event,
+    user::{check_password, check_username, User, UserToken, UserTokenScope, UserTokenTy, UserTy},
 };
-use sqlx::{Pool, Sqlite};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use sqlx::{Pool, Sqlite, SqliteExecutor};
 
-use crate::routes::{v1::models::user::generate_token, AppState};
+use crate::{
+    config::Config,
+    notifications, oidc,
+    routes::{
+        v1::models::user::{
+            generate_token, hash_password, hash_token, token_prefix, verify_password, verify_token,
+        },
+        AppState,
+    },
+    timestamp,
+};
 
-use super::{api::microservice::MicroserviceAuthorization, models::user::AuthorizedUser};
+use super::{
+    api::microservice::MicroserviceAuthorization,
+    models::user::AuthorizedUser,
+    users::{delete_user, is_username_taken},
+};
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
@@ -33,6 +56,11 @@ pub fn get_routes() -> Router<AppState> {
             post(telegram_activate_token),
         )
         .route(ClaimInviteUser::partial_path(), post(claim_invite_user))
+        .route(RefreshUserToken::partial_path(), post(refresh_user_token))
+        .route(PasswordLogin::partial_path(), post(password_login))
+        .route(SetPassword::partial_path(), put(set_password))
+        .route(OidcAuthorize::partial_path(), get(oidc_authorize))
+        .route(OidcCallback::partial_path(), post(oidc_callback))
         .route(
             ClaimInviteTelegram::partial_path(),
             post(claim_invite_telegram),
@@ -42,74 +70,291 @@ pub fn get_routes() -> Router<AppState> {
 pub async fn claim_invite(
     invite: String,
     username: String,
-    telegram_id: i64,
+    telegram_id: Option<i64>,
+    password_hash: Option<String>,
+    config: &Config,
     db: &Pool<Sqlite>,
 ) -> Result<i64, api::Error> {
-    if !check_username(&username) || telegram_id < 0 {
+    if !check_username(&username) || telegram_id.is_some_and(|v| v < 0) {
         return Err(api::Error::InvalidInput);
     }
+    if is_username_taken(&username, None, db).await {
+        return Err(api::Error::Conflict);
+    }
 
+    // Use of invite is taken first, so it can't be claimed more times than
+    // allowed by concurrent requests
+    let now = timestamp();
     let inv = sqlx::query!(
-        "select id,user_ty from userinvite where invite = ? and claimed_user_id is null",
-        invite
+        r#"update userinvite set uses = uses + 1
+            where invite = ? and revoked_at is null and (expires_at is null or expires_at > ?) and uses < max_uses
+            returning id as "id!", user_ty"#,
+        invite,
+        now
     )
-    .fetch_one(db)
+    .fetch_optional(db)
     .await
-    .ok()
+    .expect("take userinvite use")
     .map(|v| (v.id, v.user_ty));
     let Some((invite_id, user_ty)) = inv else {
         return Err(api::Error::NotFound);
     };
 
     let res = sqlx::query!(
-        "insert into user(ty,username,telegram_id) values (?,?,?)",
+        "insert into user(ty,username,telegram_id,password_hash,invite_id) values (?,?,?,?,?)",
         user_ty,
         username,
-        telegram_id
+        telegram_id,
+        password_hash,
+        invite_id
     )
     .execute(db)
     .await;
 
     let user_id = match res {
         Ok(v) => v.last_insert_rowid(),
-        Err(_) => return Err(api::Error::Conflict),
+        Err(_) => {
+            sqlx::query!(
+                "update userinvite set uses = uses - 1 where id = ?",
+                invite_id
+            )
+            .execute(db)
+            .await
+            .expect("return userinvite use");
+            return Err(api::Error::Conflict);
+        }
     };
 
-    sqlx::query!(
-        "update userinvite set claimed_user_id = ? where id = ?",
+    let created_by = sqlx::query!(
+        r#"update userinvite set claimed_user_id = coalesce(claimed_user_id, ?) where id = ?
+            returning created_by as "created_by?""#,
         user_id,
         invite_id
     )
-    .execute(db)
+    .fetch_one(db)
     .await
-    .expect("update userinvite");
+    .expect("update userinvite")
+    .created_by;
+
+    if let Some(created_by) = created_by {
+        let event = Event::InviteClaimed {
+            invite_id,
+            username,
+        };
+        notifications::enqueue(config, db, created_by, event).await;
+    }
 
     Ok(user_id)
 }
 
+/// Makes valid username that is not taken from name suggested by identity
+/// provider, suffix is added on conflict
+async fn free_username(suggested: Option<&str>, db: &Pool<Sqlite>) -> String {
+    let base: String = suggested
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' => c,
+            _ => '-',
+        })
+        .take(32)
+        .collect();
+    let base = match base.trim_matches('-') {
+        "" => "user",
+        v => v,
+    };
+
+    let mut username = base.to_owned();
+    let mut n = 1;
+    while is_username_taken(&username, None, db).await {
+        n += 1;
+        username = format!("{base}-{n}");
+    }
+    username
+}
+
+struct IdentityUser {
+    id: i64,
+    suspended_at: Option<i64>,
+}
+
+/// User linked to account of identity provider
+async fn identity_user(issuer: &str, subject: &str, db: &Pool<Sqlite>) -> Option<IdentityUser> {
+    sqlx::query_as!(
+        IdentityUser,
+        r#"select user.id, user.suspended_at from user_identity
+            join user on user_identity.user_id = user.id
+            where issuer = ? and subject = ?"#,
+        issuer,
+        subject
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select user identity")
+}
+
+/// Links account of identity provider to user, returns `false` if it is
+/// already linked
+async fn link_identity(
+    issuer: &str,
+    subject: &str,
+    user_id: i64,
+    db: impl SqliteExecutor<'_>,
+) -> bool {
+    let now = timestamp();
+    sqlx::query!(
+        r#"insert into user_identity(issuer, subject, user_id, created_at) values (?,?,?,?)
+            on conflict do nothing"#,
+        issuer,
+        subject,
+        user_id,
+        now
+    )
+    .execute(db)
+    .await
+    .expect("insert user identity")
+    .rows_affected()
+        == 1
+}
+
+/// Result of sign up that conflicted with existing user. Username suggested
+/// by provider can be taken by concurrent login of the same account, that is
+/// not an error if that login linked account.
+async fn linked_concurrently(
+    issuer: &str,
+    subject: &str,
+    db: &Pool<Sqlite>,
+) -> Result<Option<i64>, api::Error> {
+    match identity_user(issuer, subject, db).await {
+        Some(_) => Ok(None),
+        None => Err(api::Error::Conflict),
+    }
+}
+
+/// Creates user for account of identity provider on first login. Returns
+/// `None` if concurrent login of the same account linked it first, user
+/// created by this login is removed then.
+async fn sign_up_identity(
+    invite: Option<String>,
+    info: &oidc::UserInfo,
+    issuer: &str,
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> Result<Option<i64>, api::Error> {
+    let username = free_username(info.preferred_username.as_deref(), db).await;
+
+    let Some(invite) = invite else {
+        let mut tx = db.begin().await.expect("begin transaction");
+        let ty = UserTy::Unverified as i64;
+        let res = sqlx::query!("insert into user(ty, username) values (?,?)", ty, username)
+            .execute(&mut *tx)
+            .await;
+        let Ok(res) = res else {
+            tx.rollback().await.expect("rollback user");
+            return linked_concurrently(issuer, &info.sub, db).await;
+        };
+        let user_id = res.last_insert_rowid();
+        if !link_identity(issuer, &info.sub, user_id, &mut *tx).await {
+            tx.rollback().await.expect("rollback user");
+            return Ok(None);
+        }
+        tx.commit().await.expect("commit user identity");
+        return Ok(Some(user_id));
+    };
+
+    // Invite is claimed outside of transaction, as it also notifies creator
+    // of invite
+    let user_id = match claim_invite(invite, username, None, None, config, db).await {
+        Ok(v) => v,
+        Err(api::Error::Conflict) => return linked_concurrently(issuer, &info.sub, db).await,
+        Err(e) => return Err(e),
+    };
+    if link_identity(issuer, &info.sub, user_id, db).await {
+        return Ok(Some(user_id));
+    }
+    let mut tx = db.begin().await.expect("begin transaction");
+    sqlx::query!(
+        "update userinvite set uses = uses - 1 where id = (select invite_id from user where id = ?)",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("return userinvite use");
+    delete_user(user_id, &mut tx).await;
+    tx.commit().await.expect("commit user deletion");
+    Ok(None)
+}
+
+/// Parameters of issued token
+pub struct TokenParams<'a> {
+    pub ty: UserTokenTy,
+    pub scope: UserTokenScope,
+    pub label: Option<&'a str>,
+    /// Lifetime in milliseconds
+    pub lifetime: i64,
+    /// Refresh token family that token was issued with
+    pub family: Option<i64>,
+}
+
+impl TokenParams<'_> {
+    /// Default scope and configured lifetime of token type
+    pub fn new(ty: UserTokenTy, config: &Config) -> Self {
+        Self {
+            ty,
+            scope: ty.default_scope(),
+            label: None,
+            lifetime: config.token.lifetime(ty),
+            family: None,
+        }
+    }
+}
+
 pub async fn issue_token(
     user_id: i64,
     ty: UserTokenTy,
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> IssueUserTokenResponse {
+    issue_scoped_token(user_id, TokenParams::new(ty, config), config, db).await
+}
+
+/// Issues token with parameters set by caller
+pub async fn issue_scoped_token(
+    user_id: i64,
+    TokenParams {
+        ty,
+        scope,
+        label,
+        lifetime,
+        family,
+    }: TokenParams<'_>,
+    config: &Config,
     db: &Pool<Sqlite>,
 ) -> IssueUserTokenResponse {
     let token = generate_token();
+    let (hash, prefix) = (hash_token(config, &token), token_prefix(&token));
     let issued_at = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_millis() as i64;
-    let expires_in = issued_at + ty.lifetime();
+    let expires_in = issued_at + lifetime;
 
-    let ity = ty as i64;
+    let (ity, scope) = (ty as i64, scope.bits());
     sqlx::query!(
-        "insert into usertoken(user_id,token,issued_at,ty) values (?,?,?,?)",
+        "insert into usertoken(user_id,token,token_prefix,issued_at,expires_at,ty,scope,label,family) values (?,?,?,?,?,?,?,?,?)",
         user_id,
-        token,
+        hash,
+        prefix,
         issued_at,
-        ity
+        expires_in,
+        ity,
+        scope,
+        label,
+        family
     )
     .execute(db)
     .await
-    .expect("insert user token from telegram activation");
+    .expect("insert user token");
 
     IssueUserTokenResponse {
         issued_at,
@@ -117,44 +362,439 @@ pub async fn issue_token(
         user_id,
         token,
         ty,
+        refresh_token: None,
+        refresh_expires_at: None,
     }
 }
 
-pub async fn claim_invite_user(
+/// Issues access token with refresh token of `family`
+async fn issue_token_pair(
+    user_id: i64,
+    family: i64,
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> IssueUserTokenResponse {
+    let params = TokenParams {
+        family: Some(family),
+        ..TokenParams::new(UserTokenTy::Access, config)
+    };
+    let mut response = issue_scoped_token(user_id, params, config, db).await;
+
+    let token = generate_token();
+    let (hash, prefix) = (hash_token(config, &token), token_prefix(&token));
+    let issued_at = timestamp();
+    let expires_at = issued_at + config.token.refresh_lifetime as i64 * 1000;
+
+    sqlx::query!(
+        "insert into refresh_token(user_id,family,token_prefix,token,issued_at,expires_at) values (?,?,?,?,?,?)",
+        user_id,
+        family,
+        prefix,
+        hash,
+        issued_at,
+        expires_at
+    )
+    .execute(db)
+    .await
+    .expect("insert refresh token");
+
+    response.refresh_token = Some(token);
+    response.refresh_expires_at = Some(expires_at);
+    response
+}
+
+/// Issues token on login. It is session token or, if
+/// [`TokenConfig::refresh`](crate::config::TokenConfig::refresh) is set,
+/// access token with refresh token.
+pub async fn issue_session(
+    user_id: i64,
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> IssueUserTokenResponse {
+    if !config.token.refresh {
+        return issue_token(user_id, UserTokenTy::UserLimited, config, db).await;
+    }
+
+    let family = rand::thread_rng().gen_range(1..i64::MAX);
+    issue_token_pair(user_id, family, config, db).await
+}
+
+/// Revokes refresh tokens of family and access tokens issued with them
+pub async fn revoke_family(family: i64, db: &Pool<Sqlite>) {
+    sqlx::query!("delete from refresh_token where family = ?", family)
+        .execute(db)
+        .await
+        .expect("delete refresh token family");
+    sqlx::query!("delete from usertoken where family = ?", family)
+        .execute(db)
+        .await
+        .expect("delete access tokens of family");
+}
+
+pub async fn refresh_user_token(
+    State(AppState { db, config, .. }): State<AppState>,
+    Json(RefreshUserTokenBody {
+        user_id,
+        refresh_token,
+    }): Json<<RefreshUserToken as Endpoint>::Body>,
+) -> api::Response<<RefreshUserToken as Endpoint>::Response> {
+    let prefix = token_prefix(&refresh_token);
+    let token = sqlx::query!(
+        r#"select refresh_token.id, family, token, expires_at, used_at, user.suspended_at
+            from refresh_token
+            join user on refresh_token.user_id = user.id
+            where user_id = ? and token_prefix = ?"#,
+        user_id,
+        prefix
+    )
+    .fetch_all(&db)
+    .await
+    .expect("select refresh tokens")
+    .into_iter()
+    .find(|v| verify_token(config, &refresh_token, &v.token));
+
+    let now = timestamp();
+    let Some(token) = token.filter(|v| v.expires_at >= now) else {
+        return api::Response::error(api::Error::InvalidToken);
+    };
+    if token.suspended_at.is_some() {
+        return api::Response::error(api::Error::Suspended);
+    }
+
+    // Token that was already rotated is likely stolen, so whole family
+    // is revoked
+    let rotated = sqlx::query!(
+        "update refresh_token set used_at = ? where id = ? and used_at is null",
+        now,
+        token.id
+    )
+    .execute(&db)
+    .await
+    .expect("rotate refresh token")
+    .rows_affected();
+    if token.used_at.is_some() || rotated == 0 {
+        revoke_family(token.family, &db).await;
+        return api::Response::error(api::Error::InvalidToken);
+    }
+
+    api::Response::Success(issue_token_pair(user_id, token.family, config, &db).await)
+}
+
+/// Hashes password in blocking task
+async fn hash_password_blocking(password: String) -> String {
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .expect("hash password")
+}
+
+/// How long login with identity provider may take, in milliseconds
+const OIDC_STATE_LIFETIME: i64 = 10 * 60 * 1000;
+
+/// Cookie that binds `state` of login to browser that started it
+const OIDC_STATE_COOKIE: &str = "dp_oidc_state";
+
+/// Returns value of cookie in request
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|v| v.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+pub async fn oidc_authorize(
+    State(AppState {
+        db, config, oidc, ..
+    }): State<AppState>,
+    Query(OidcAuthorizeQuery { invite }): Query<<OidcAuthorize as Endpoint>::Query>,
+) -> Result<impl IntoResponse, api::Response<<OidcAuthorize as Endpoint>::Response>> {
+    let Some(oidc_config) = &config.oidc else {
+        return Err(api::Response::error(api::Error::NotFound));
+    };
+
+    let login = oidc::Login {
+        state: generate_token(),
+        nonce: generate_token(),
+        code_verifier: generate_token(),
+    };
+    let url = match oidc.authorization_url(oidc_config, &login).await {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed to start OIDC login: {e}");
+            return Err(api::Response::error(api::Error::Internal));
+        }
+    };
+
+    let now = timestamp();
+    sqlx::query!("delete from oidc_state where expires_at < ?", now)
+        .execute(&db)
+        .await
+        .expect("delete expired oidc states");
+    let expires_at = now + OIDC_STATE_LIFETIME;
+    sqlx::query!(
+        "insert into oidc_state(state, invite, nonce, code_verifier, expires_at) values (?,?,?,?,?)",
+        login.state,
+        invite,
+        login.nonce,
+        login.code_verifier,
+        expires_at
+    )
+    .execute(&db)
+    .await
+    .expect("insert oidc state");
+
+    let cookie = format!(
+        "{OIDC_STATE_COOKIE}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+        login.state,
+        OIDC_STATE_LIFETIME / 1000
+    );
+    let response: api::Response<<OidcAuthorize as Endpoint>::Response> =
+        api::Response::Success(OidcAuthorizeResponse {
+            url,
+            state: login.state,
+        });
+    Ok(([(header::SET_COOKIE, cookie)], response))
+}
+
+/// Finishes login started by [`oidc_authorize`] in the same browser, cookie
+/// of login is cleared
+pub async fn oidc_callback(
+    State(AppState {
+        db, config, oidc, ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    Json(OidcCallbackBody { code, state }): Json<<OidcCallback as Endpoint>::Body>,
+) -> impl IntoResponse {
+    let clear = format!("{OIDC_STATE_COOKIE}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax");
+    (
+        [(header::SET_COOKIE, clear)],
+        finish_oidc_login(db, config, oidc, &headers, code, state).await,
+    )
+}
+
+async fn finish_oidc_login(
+    db: Pool<Sqlite>,
+    config: &'static Config,
+    oidc: oidc::Provider,
+    headers: &HeaderMap,
+    code: String,
+    state: String,
+) -> api::Response<<OidcCallback as Endpoint>::Response, &'static str> {
+    let Some(oidc_config) = &config.oidc else {
+        return api::Response::error(api::Error::NotFound);
+    };
+
+    // Login started in another browser is not finished even with valid
+    // `state`
+    if cookie(headers, OIDC_STATE_COOKIE) != Some(state.as_str()) {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "`state` does not match login started in this browser",
+        );
+    }
+
+    let now = timestamp();
+    let login = sqlx::query!(
+        "delete from oidc_state where state = ? returning invite, nonce, code_verifier, expires_at",
+        state
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("take oidc state")
+    .filter(|v| v.expires_at >= now);
+    let Some(login) = login else {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "unknown or expired `state`",
+        );
+    };
+    let invite = login.invite;
+    let login = oidc::Login {
+        state,
+        nonce: login.nonce,
+        code_verifier: login.code_verifier,
+    };
+
+    let info = match oidc.exchange_code(oidc_config, &login, &code).await {
+        Ok(v) => v,
+        Err(oidc::Error::Rejected) => return api::Response::error(api::Error::InvalidCredentials),
+        Err(e) => {
+            eprintln!("Failed to finish OIDC login: {e}");
+            return api::Response::error(api::Error::Internal);
+        }
+    };
+
+    let user = match identity_user(&oidc_config.issuer, &info.sub, &db).await {
+        Some(v) => v,
+        None => match sign_up_identity(invite, &info, &oidc_config.issuer, config, &db).await {
+            Ok(Some(id)) => IdentityUser {
+                id,
+                suspended_at: None,
+            },
+            Ok(None) => identity_user(&oidc_config.issuer, &info.sub, &db)
+                .await
+                .expect("identity linked by concurrent login"),
+            Err(e) => return api::Response::error(e),
+        },
+    };
+    if user.suspended_at.is_some() {
+        return api::Response::error(api::Error::Suspended);
+    }
+
+    api::Response::Success(issue_token(user.id, UserTokenTy::UserLimited, config, &db).await)
+}
+
+pub async fn password_login(
+    State(AppState { db, config, .. }): State<AppState>,
+    Json(PasswordLoginBody { username, password }): Json<<PasswordLogin as Endpoint>::Body>,
+) -> api::Response<<PasswordLogin as Endpoint>::Response> {
+    // Unknown users are checked against dummy hash, so response time does not
+    // tell which usernames exist
+    static DUMMY_HASH: Lazy<String> = Lazy::new(|| hash_password("dummy password"));
+
+    let user = sqlx::query!(
+        "select id, password_hash, suspended_at from user where username = ?",
+        username
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("select user password");
+
+    let hash = user.as_ref().and_then(|v| v.password_hash.clone());
+    let valid = tokio::task::spawn_blocking(move || match hash {
+        Some(hash) => verify_password(&password, &hash),
+        None => {
+            verify_password(&password, &DUMMY_HASH);
+            false
+        }
+    })
+    .await
+    .expect("verify password");
+
+    let Some(user) = user.filter(|_| valid) else {
+        return api::Response::error(api::Error::InvalidCredentials);
+    };
+    if user.suspended_at.is_some() {
+        return api::Response::error(api::Error::Suspended);
+    }
+
+    api::Response::Success(issue_session(user.id, config, &db).await)
+}
+
+pub async fn set_password(
     State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(SetPasswordBody {
+        password,
+        current_password,
+    }): Json<<SetPassword as Endpoint>::Body>,
+) -> api::Response<api::EmptyErrorData, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+    if !check_password(&password) {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "length of `password` should be in range 8..=128",
+        );
+    }
+
+    let current_hash = sqlx::query!("select password_hash from user where id = ?", user.id)
+        .fetch_one(&db)
+        .await
+        .expect("select user password")
+        .password_hash;
+    if let Some(hash) = current_hash {
+        let Some(current_password) = current_password else {
+            return api::Response::error_description(
+                api::Error::InvalidCredentials,
+                "`current_password` is required to change password",
+            );
+        };
+        let valid = tokio::task::spawn_blocking(move || verify_password(&current_password, &hash))
+            .await
+            .expect("verify password");
+        if !valid {
+            return api::Response::error(api::Error::InvalidCredentials);
+        }
+    }
+
+    let hash = hash_password_blocking(password).await;
+    sqlx::query!(
+        "update user set password_hash = ? where id = ?",
+        hash,
+        user.id
+    )
+    .execute(&db)
+    .await
+    .expect("update user password");
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn claim_invite_user(
+    State(AppState { db, config, .. }): State<AppState>,
     Json(ClaimInviteBody {
         invite,
         username,
         telegram_id,
+        password,
     }): Json<<ClaimInviteUser as Endpoint>::Body>,
-) -> api::Response<<ClaimInviteUser as Endpoint>::Response> {
-    let user_id = match claim_invite(invite, username, telegram_id, &db).await {
-        Ok(v) => v,
-        Err(e) => return api::Response::error(e),
+) -> api::Response<<ClaimInviteUser as Endpoint>::Response, &'static str> {
+    let password_hash = match password {
+        Some(v) if !check_password(&v) => {
+            return api::Response::error_description(
+                api::Error::InvalidInput,
+                "length of `password` should be in range 8..=128",
+            )
+        }
+        Some(v) => Some(hash_password_blocking(v).await),
+        None if telegram_id.is_none() => {
+            return api::Response::error_description(
+                api::Error::InvalidInput,
+                "`telegram_id` or `password` should be set",
+            )
+        }
+        None => None,
     };
 
-    api::Response::Success(issue_token(user_id, UserTokenTy::UserLimited, &db).await)
+    let user_id =
+        match claim_invite(invite, username, telegram_id, password_hash, config, &db).await {
+            Ok(v) => v,
+            Err(e) => return api::Response::error(e),
+        };
+
+    api::Response::Success(issue_session(user_id, config, &db).await)
 }
 
 pub async fn claim_invite_telegram(
     ms: MicroserviceAuthorization,
-    State(AppState { db, .. }): State<AppState>,
+    State(AppState { db, config, .. }): State<AppState>,
     Json(ClaimInviteBody {
         invite,
         username,
         telegram_id,
+        ..
     }): Json<ClaimInviteBody>,
 ) -> api::Response<<ClaimInviteTelegram as Endpoint>::Response> {
-    if !matches!(ms, MicroserviceAuthorization::Telegram) {
+    if !ms.is(microservice::TELEGRAM) {
         return api::Response::error(api::Error::AuthorizationRequired);
     }
+    if telegram_id.is_none() {
+        return api::Response::error(api::Error::InvalidInput);
+    }
 
-    let user_id = match claim_invite(invite, username, telegram_id, &db).await {
+    let user_id = match claim_invite(invite, username, telegram_id, None, config, &db).await {
         Ok(v) => v,
         Err(e) => return api::Response::error(e),
     };
 
-    api::Response::Success(issue_token(user_id, UserTokenTy::TelegramAuthorization, &db).await)
+    api::Response::Success(
+        issue_token(user_id, UserTokenTy::TelegramAuthorization, config, &db).await,
+    )
 }
 
 pub async fn telegram_activate_token(
@@ -164,7 +804,7 @@ pub async fn telegram_activate_token(
             id: token_id, ty, ..
         },
     }: AuthorizedUser,
-    State(AppState { db, .. }): State<AppState>,
+    State(AppState { db, config, .. }): State<AppState>,
 ) -> api::Response<<ClaimInviteUser as Endpoint>::Response> {
     if !matches!(ty, UserTokenTy::TelegramAuthorization) {
         return api::Response::error(api::Error::AuthorizationRequired);
@@ -175,26 +815,34 @@ pub async fn telegram_activate_token(
         .await
         .expect("delete user token from telegram activation");
 
-    api::Response::Success(issue_token(user_id, UserTokenTy::UserLimited, &db).await)
+    api::Response::Success(issue_session(user_id, config, &db).await)
 }
 
 pub async fn telegram_issue_token(
     ms: MicroserviceAuthorization,
     Query(IssueUserTokenQuery { telegram_id }): Query<<TelegramIssueToken as Endpoint>::Query>,
-    State(AppState { db, .. }): State<AppState>,
+    State(AppState { db, config, .. }): State<AppState>,
 ) -> api::Response<<TelegramIssueToken as Endpoint>::Response> {
-    if !matches!(ms, MicroserviceAuthorization::Telegram) {
+    if !ms.is(microservice::TELEGRAM) {
         return api::Response::error(api::Error::AuthorizationRequired);
     }
 
-    let user_id = sqlx::query!("select id from user where telegram_id = ?;", telegram_id)
-        .fetch_one(&db)
-        .await
-        .map(|v| v.id);
+    let user = sqlx::query!(
+        "select id, suspended_at from user where telegram_id = ?;",
+        telegram_id
+    )
+    .fetch_one(&db)
+    .await;
 
-    let Ok(user_id) = user_id else {
+    let Ok(user) = user else {
         return api::Response::error(api::Error::NotFound);
     };
+    if user.suspended_at.is_some() {
+        return api::Response::error(api::Error::Suspended);
+    }
+    let user_id = user.id;
 
-    api::Response::Success(issue_token(user_id, UserTokenTy::TelegramAuthorization, &db).await)
+    api::Response::Success(
+        issue_token(user_id, UserTokenTy::TelegramAuthorization, config, &db).await,
+    )
 }
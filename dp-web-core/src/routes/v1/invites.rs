@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, put},
+    Json, Router,
+};
+use dp_core::v1::{
+    api,
+    endpoint::{
+        invites::{
+            CreateInvite, CreateInviteBody, InviteInfo, InvitePath, ListInvites, RevokeInvite,
+        },
+        Endpoint,
+    },
+    user::{UserTokenScope, UserTy},
+};
+
+use crate::{routes::AppState, timestamp};
+
+use super::models::user::{generate_token, AuthorizedUser};
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new()
+        .route(ListInvites::partial_path(), get(list_invites))
+        .route(CreateInvite::partial_path(), put(create_invite))
+        .route(RevokeInvite::partial_path(), delete(revoke_invite))
+}
+
+pub async fn list_invites(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+) -> api::Response<<ListInvites as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_INVITES) {
+        return api::Response::error(e);
+    }
+
+    let list = sqlx::query!(
+        "select id, user_ty, reason, invite, issued_at, expires_at, max_uses, uses, revoked_at from userinvite where created_by = ? order by id desc",
+        user.id
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|v| InviteInfo {
+        id: v.id,
+        user_ty: UserTy::from_bits(v.user_ty),
+        reason: v.reason,
+        invite: v.invite,
+        issued_at: v.issued_at,
+        expires_at: v.expires_at,
+        max_uses: v.max_uses,
+        uses: v.uses,
+        revoked_at: v.revoked_at,
+    })
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn create_invite(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(CreateInviteBody {
+        reason,
+        max_uses,
+        expires_in,
+    }): Json<<CreateInvite as Endpoint>::Body>,
+) -> api::Response<<CreateInvite as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_INVITES) {
+        return api::Response::error(e);
+    }
+    if user.ty != UserTy::Normal {
+        return api::Response::error(api::Error::Forbidden);
+    }
+
+    if !matches!(reason.chars().count(), 1..=200) {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "lenght of `reason` should be in range 1..=200",
+        );
+    }
+    let max_lifetime = config.invites.lifetime as i64 * 1000;
+    let lifetime = match expires_in {
+        None => max_lifetime,
+        Some(v @ 1..) if v <= max_lifetime => v,
+        Some(_) => {
+            return api::Response::error_description(
+                api::Error::InvalidInput,
+                "`expires_in` should be positive and not longer than allowed by server",
+            )
+        }
+    };
+    let max_uses = max_uses.unwrap_or(1);
+    if max_uses < 1 {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "`max_uses` should be positive",
+        );
+    }
+
+    let issued_at = timestamp();
+    let used_quota = sqlx::query_scalar!(
+        r#"select coalesce(sum(case when revoked_at is null and (expires_at is null or expires_at > ?)
+                then max_uses else uses end), 0) as "used!: i64"
+            from userinvite where created_by = ?"#,
+        issued_at,
+        user.id
+    )
+    .fetch_one(&db)
+    .await
+    .expect("select used invite quota");
+    if used_quota + max_uses > config.invites.quota {
+        return api::Response::error_description(
+            api::Error::Forbidden,
+            "quota of invites is exceeded",
+        );
+    }
+
+    let invite = generate_token();
+    let user_ty = UserTy::Unverified;
+    let (ity, expires_at) = (user_ty as i64, issued_at + lifetime);
+    let id = sqlx::query!(
+        "insert into userinvite(user_ty,reason,invite,issued_at,expires_at,max_uses,created_by) values (?,?,?,?,?,?,?)",
+        ity,
+        reason,
+        invite,
+        issued_at,
+        expires_at,
+        max_uses,
+        user.id
+    )
+    .execute(&db)
+    .await
+    .expect("insert userinvite")
+    .last_insert_rowid();
+
+    api::Response::Success(InviteInfo {
+        id,
+        user_ty,
+        reason,
+        invite,
+        issued_at,
+        expires_at: Some(expires_at),
+        max_uses,
+        uses: 0,
+        revoked_at: None,
+    })
+}
+
+pub async fn revoke_invite(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(InvitePath { id }): Path<InvitePath>,
+) -> api::Response {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_INVITES) {
+        return api::Response::error(e);
+    }
+
+    let revoked_at = timestamp();
+    let revoked = sqlx::query!(
+        "update userinvite set revoked_at = coalesce(revoked_at, ?) where id = ? and created_by = ?",
+        revoked_at,
+        id,
+        user.id
+    )
+    .execute(&db)
+    .await
+    .expect("revoke userinvite")
+    .rows_affected();
+
+    match revoked {
+        0 => api::Response::error(api::Error::NotFound),
+        _ => api::Response::Success(api::EmptyErrorData),
+    }
+}
@@ -1,17 +1,28 @@
-use axum::Router;
+use axum::{middleware, Router};
 use dp_core::v1::endpoint;
 
 use super::AppState;
 
+pub mod admin;
 pub mod api;
 pub mod auth;
+pub mod invites;
 pub mod models;
+pub mod moderation;
 pub mod projects;
 pub mod users;
+pub mod webhooks;
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .nest(endpoint::auth::PREFIX, auth::get_routes())
         .nest(endpoint::user::PREFIX, users::get_routes())
-        .nest(endpoint::projects::PREFIX, projects::get_routes())
+        .nest(endpoint::invites::PREFIX, invites::get_routes())
+        .nest(endpoint::moderation::PREFIX, moderation::get_routes())
+        .nest(endpoint::admin::PREFIX, admin::get_routes())
+        .nest(
+            endpoint::projects::PREFIX,
+            projects::get_routes().merge(webhooks::get_routes()),
+        )
+        .layer(middleware::from_fn(api::microservice::hash_signed_body))
 }
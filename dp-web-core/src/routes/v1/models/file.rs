@@ -0,0 +1,158 @@
+//! Stored files served with `Range` and conditional requests support
+
+use std::{
+    io::SeekFrom,
+    ops::RangeInclusive,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dp_core::v1::api;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+/// Metadata of served file
+pub struct FileMeta<'a> {
+    pub content_type: &'static str,
+    /// Name of file shown to user, only ASCII letters, digits and `.-_ `
+    /// are kept
+    pub file_name: &'a str,
+    pub etag: String,
+    /// Modification time in UNIX milliseconds
+    pub last_modified: i64,
+}
+
+/// Parses `Range` header. Returns `None` if header should be ignored
+/// (malformed or multiple ranges) and `Some(Err(()))` if range is not
+/// satisfiable.
+fn parse_range(value: &str, len: u64) -> Option<Result<RangeInclusive<u64>, ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            len.saturating_sub(suffix)..=len - 1
+        }
+        (start, "") => start.parse::<u64>().ok()?..=len.saturating_sub(1),
+        (start, end) => {
+            let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+            if start > end {
+                return None;
+            }
+            start..=end.min(len.saturating_sub(1))
+        }
+    };
+
+    if *range.start() >= len {
+        Some(Err(()))
+    } else {
+        Some(Ok(range))
+    }
+}
+
+fn etag_matches(value: &str, etag: &str) -> bool {
+    value
+        .split(',')
+        .map(|v| v.trim().trim_start_matches("W/"))
+        .any(|v| v == "*" || v == etag)
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Serves file at `path`. Missing file results in [`api::Error::NotFound`].
+pub async fn serve_file(
+    path: &Path,
+    meta: FileMeta<'_>,
+    headers: &HeaderMap,
+) -> Result<Response, api::EmptyResponse> {
+    let not_found = || api::EmptyResponse::error(api::Error::NotFound);
+    let mut file = tokio::fs::File::open(path).await.map_err(|_| not_found())?;
+    let len = file.metadata().await.map_err(|_| not_found())?.len();
+
+    let last_modified = UNIX_EPOCH + Duration::from_secs((meta.last_modified / 1000) as u64);
+    let last_modified_str = httpdate::fmt_http_date(last_modified);
+    let file_name: String = meta
+        .file_name
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' | '_' | ' ' => c,
+            _ => '_',
+        })
+        .collect();
+
+    let mut response_headers = HeaderMap::new();
+    let mut insert = |name, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response_headers.insert(name, value);
+        }
+    };
+    insert(header::ETAG, &meta.etag);
+    insert(header::LAST_MODIFIED, &last_modified_str);
+    insert(header::ACCEPT_RANGES, "bytes");
+
+    let not_modified = match header_str(headers, header::IF_NONE_MATCH) {
+        Some(v) => etag_matches(v, &meta.etag),
+        None => header_str(headers, header::IF_MODIFIED_SINCE)
+            .and_then(|v| httpdate::parse_http_date(v).ok())
+            .is_some_and(|v| last_modified <= v),
+    };
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    let range_allowed = match header_str(headers, header::IF_RANGE) {
+        Some(v) => v == meta.etag || v == last_modified_str,
+        None => true,
+    };
+    let range = header_str(headers, header::RANGE)
+        .filter(|_| range_allowed)
+        .and_then(|v| parse_range(v, len));
+
+    let (status, range) = match range {
+        Some(Ok(range)) => {
+            insert(
+                header::CONTENT_RANGE,
+                &format!("bytes {}-{}/{len}", range.start(), range.end()),
+            );
+            (StatusCode::PARTIAL_CONTENT, range)
+        }
+        Some(Err(())) => {
+            insert(header::CONTENT_RANGE, &format!("bytes */{len}"));
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response());
+        }
+        None => (StatusCode::OK, 0..=len.saturating_sub(1)),
+    };
+    let size = if len == 0 {
+        0
+    } else {
+        range.end() - range.start() + 1
+    };
+
+    insert(header::CONTENT_TYPE, meta.content_type);
+    insert(header::CONTENT_LENGTH, &size.to_string());
+    insert(
+        header::CONTENT_DISPOSITION,
+        &format!("inline; filename=\"{file_name}\""),
+    );
+
+    file.seek(SeekFrom::Start(*range.start()))
+        .await
+        .map_err(|_| api::EmptyResponse::error(api::Error::Internal))?;
+    let body = Body::from_stream(ReaderStream::new(file.take(size)));
+
+    Ok((status, response_headers, body).into_response())
+}
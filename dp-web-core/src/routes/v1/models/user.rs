@@ -1,5 +1,9 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2, PasswordHash,
+};
 use axum::{
     async_trait,
     extract::{FromRequestParts, State},
@@ -7,11 +11,59 @@ use axum::{
 };
 use dp_core::v1::{
     api,
-    user::{User, UserToken, UserTokenScope, UserTokenTy, UserTy},
+    user::{User, UserRole, UserToken, UserTokenScope, UserTokenTy, UserTy},
 };
+use hmac::{Hmac, Mac};
 use rand::Rng;
+use sha2::Sha256;
+
+use crate::{config::Config, routes::AppState};
+
+/// Length of token part stored as is to find token
+pub const TOKEN_PREFIX_LEN: usize = 8;
+
+type TokenMac = Hmac<Sha256>;
+
+fn token_mac(config: &Config, token: &str) -> TokenMac {
+    let mut mac =
+        TokenMac::new_from_slice(config.token_key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(token.as_bytes());
+    mac
+}
 
-use crate::routes::AppState;
+/// Returns keyed hash of token that stored in database
+pub fn hash_token(config: &Config, token: &str) -> String {
+    hex::encode(token_mac(config, token).finalize().into_bytes())
+}
+
+/// Checks token against stored hash in constant time
+pub fn verify_token(config: &Config, token: &str, hash: &str) -> bool {
+    hex::decode(hash).is_ok_and(|hash| token_mac(config, token).verify_slice(&hash).is_ok())
+}
+
+/// Returns Argon2 hash of password in PHC string format. It is slow, so
+/// should be called in blocking task.
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("hash password")
+        .to_string()
+}
+
+/// Checks password against stored hash, should be called in blocking task
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Returns part of token used to find it in database
+pub fn token_prefix(token: &str) -> &str {
+    token.get(..TOKEN_PREFIX_LEN).unwrap_or(token)
+}
 
 pub struct AuthorizedUser {
     pub user: User,
@@ -20,15 +72,16 @@ pub struct AuthorizedUser {
 
 #[async_trait]
 impl FromRequestParts<AppState> for AuthorizedUser {
-    type Rejection = api::EmptyResponse;
+    type Rejection = api::Response<api::EmptyErrorData, String>;
 
     async fn from_request_parts(
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let State(AppState { db, .. }) = State::<AppState>::from_request_parts(parts, state)
-            .await
-            .expect("state should not fail");
+        let State(AppState { db, config, .. }) =
+            State::<AppState>::from_request_parts(parts, state)
+                .await
+                .expect("state should not fail");
 
         let token = parts
             .headers
@@ -39,20 +92,31 @@ impl FromRequestParts<AppState> for AuthorizedUser {
             .map(|(i, t)| (i.parse::<i64>().ok(), t));
 
         let Some((Some(user_id), token)) = token else {
-            return Err(api::EmptyResponse::error(api::Error::AuthorizationRequired));
+            return Err(api::Response::error(api::Error::AuthorizationRequired));
         };
 
+        let prefix = token_prefix(token);
         let res = sqlx::query!(
-            r#"select usertoken.*, user.ty as userty, user.username as username, user.telegram_id as telegram_id
+            r#"select usertoken.*, user.ty as userty, user.username as username, user.telegram_id as telegram_id, user.role as userrole, user.suspended_at, user.suspension_reason
                 from usertoken
                 join user on usertoken.user_id = user.id
-                where usertoken.token = ? and user.id = ?;"#,
-            token,
+                where usertoken.token_prefix = ? and user.id = ?;"#,
+            prefix,
             user_id
         )
-        .fetch_one(&db)
+        .fetch_all(&db)
         .await
-        .map_err(|_| api::EmptyResponse::error(api::Error::InvalidToken))?;
+        .expect("select user tokens")
+        .into_iter()
+        .find(|v| verify_token(config, token, &v.token))
+        .ok_or(api::Response::error(api::Error::InvalidToken))?;
+
+        if res.suspended_at.is_some() {
+            return Err(match res.suspension_reason {
+                Some(reason) => api::Response::error_description(api::Error::Suspended, reason),
+                None => api::Response::error(api::Error::Suspended),
+            });
+        }
 
         let token_ty = UserTokenTy::from_bits(res.ty);
 
@@ -61,27 +125,50 @@ impl FromRequestParts<AppState> for AuthorizedUser {
             .expect("Time went backwards")
             .as_millis() as i64;
 
-        if token_ty.lifetime() + res.issued_at < time {
+        if res.expires_at < time {
             _ = sqlx::query!("delete from usertoken where id = ?", res.id)
                 .execute(&db)
                 .await;
-            return Err(api::EmptyResponse::error(api::Error::InvalidToken));
+            return Err(api::Response::error(api::Error::InvalidToken));
         }
 
+        let expires_at = match token_ty {
+            UserTokenTy::UserLimited if config.token.sliding_expiry => {
+                time + config.token.lifetime(token_ty)
+            }
+            _ => res.expires_at,
+        };
+        _ = sqlx::query!(
+            "update usertoken set last_used_at = ?, expires_at = ? where id = ?",
+            time,
+            expires_at,
+            res.id
+        )
+        .execute(&db)
+        .await;
+
+        let (user_ty, role) = (
+            UserTy::from_bits(res.userty),
+            UserRole::from_bits(res.userrole),
+        );
         Ok(Self {
             token: UserToken {
                 id: res.id,
                 ty: token_ty,
                 user_id,
                 issued_at: res.issued_at,
-                scope: UserTokenScope::from_bits_retain(res.scope),
+                expires_at,
+                scope: UserTokenScope::from_bits_retain(res.scope)
+                    & user_ty.max_scope()
+                    & role.max_scope(),
                 token: token.to_owned(),
             },
             user: User {
                 id: user_id,
-                ty: UserTy::from_bits(res.userty),
+                ty: user_ty,
                 username: res.username,
                 telegram_id: res.telegram_id,
+                role,
             },
         })
     }
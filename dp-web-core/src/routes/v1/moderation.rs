@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Router,
+};
+use dp_core::v1::{
+    api,
+    endpoint::{
+        moderation::{ApproveUser, ListPendingUsers, PendingUser, PendingUserPath, RejectUser},
+        Endpoint,
+    },
+    notification::Event,
+    user::{User, UserRole, UserToken, UserTokenScope, UserTy},
+};
+use sqlx::{Pool, Sqlite};
+
+use crate::{notifications, routes::AppState};
+
+use super::{models::user::AuthorizedUser, users::delete_user};
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new()
+        .route(ListPendingUsers::partial_path(), get(list_pending_users))
+        .route(ApproveUser::partial_path(), post(approve_user))
+        .route(RejectUser::partial_path(), post(reject_user))
+}
+
+/// Checks that user is moderator and token allows moderation
+fn require_moderator(user: &User, token: &UserToken) -> Result<(), api::Error> {
+    token.scope.require(UserTokenScope::MODERATE)?;
+    if user.role < UserRole::Moderator {
+        return Err(api::Error::Forbidden);
+    }
+    Ok(())
+}
+
+/// Returns type of user that waits for approval
+async fn fetch_pending(id: i64, db: &Pool<Sqlite>) -> Result<UserTy, api::Error> {
+    let ty = sqlx::query!("select ty from user where id = ?", id)
+        .fetch_optional(db)
+        .await
+        .expect("select user")
+        .map(|v| UserTy::from_bits(v.ty));
+
+    match ty {
+        Some(UserTy::Normal) => Err(api::Error::Conflict),
+        Some(v) => Ok(v),
+        None => Err(api::Error::NotFound),
+    }
+}
+
+pub async fn list_pending_users(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+) -> api::Response<<ListPendingUsers as Endpoint>::Response> {
+    if let Err(e) = require_moderator(&user, &token) {
+        return api::Response::error(e);
+    }
+
+    let normal = UserTy::Normal as i64;
+    let list = sqlx::query!(
+        r#"select user.id, user.username, user.ty, userinvite.reason as "invite_reason?", creator.username as "invited_by?"
+            from user
+            left join userinvite on user.invite_id = userinvite.id
+            left join user as creator on userinvite.created_by = creator.id
+            where user.ty != ? order by user.id"#,
+        normal
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|v| PendingUser {
+        id: v.id,
+        username: v.username,
+        ty: UserTy::from_bits(v.ty),
+        invite_reason: v.invite_reason,
+        invited_by: v.invited_by,
+    })
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn approve_user(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(PendingUserPath { id }): Path<PendingUserPath>,
+) -> api::Response {
+    if let Err(e) = require_moderator(&user, &token) {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_pending(id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let normal = UserTy::Normal as i64;
+    sqlx::query!("update user set ty = ? where id = ?", normal, id)
+        .execute(&db)
+        .await
+        .expect("approve user");
+
+    let event = Event::ModerationDecision { approved: true };
+    notifications::enqueue(config, &db, id, event).await;
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn reject_user(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(PendingUserPath { id }): Path<PendingUserPath>,
+) -> api::Response<api::EmptyErrorData, &'static str> {
+    if let Err(e) = require_moderator(&user, &token) {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_pending(id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let authored = sqlx::query!("select id from project where author_id = ? limit 1", id)
+        .fetch_optional(&db)
+        .await
+        .expect("select projects of user")
+        .is_some();
+    if authored {
+        return api::Response::error_description(
+            api::Error::Conflict,
+            "user has projects, they should be deleted first",
+        );
+    }
+
+    // Notification is queued with Telegram account of user, so it is
+    // delivered after user is deleted
+    let event = Event::ModerationDecision { approved: false };
+    notifications::enqueue(config, &db, id, event).await;
+    let mut conn = db.acquire().await.expect("acquire connection");
+    delete_user(id, &mut conn).await;
+
+    api::Response::Success(api::EmptyErrorData)
+}
@@ -1,121 +1,998 @@
+use std::io;
+
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    routing::{delete, get, put},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
 use dp_core::v1::{
     api,
     endpoint::{
         projects::{
-            CreateProject, CreateProjectBody, DeleteProject, ListProjects, ProjectInfo,
-            ProjectListQuery, ProjectPath,
+            AddMember, AddMemberBody, BuildInfo, CreateProject, CreateProjectBody, DeleteProject,
+            DownloadSource, GetBuild, GetBuildLog, GetProject, GetProjectPdf, GetSourcePdf,
+            ListMembers, ListProjects, ListPublicProjects, ListSources, ListTelegramProjects,
+            MemberInfo, MemberPath, ProjectInfo, ProjectListQuery, ProjectPath, RemoveMember,
+            SourceInfo, SourcePath, StartBuild, TelegramProjectListQuery, UpdateProject,
+            UpdateProjectBody, UploadSource, UploadSourceQuery,
         },
         Endpoint,
     },
-    project::ProjectTy,
+    microservice,
+    notification::Event,
+    project::{
+        check_entrypoint, check_project_title, BuildStatus, ProjectRole, ProjectTy,
+        ProjectVisibility, SourceFormat,
+    },
+    user::UserTokenScope,
+    webhook,
 };
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection};
 
-use crate::routes::AppState;
+use crate::{config::Config, notifications, routes::AppState, sources, timestamp, webhooks};
 
-use super::models::user::AuthorizedUser;
+use super::{
+    api::microservice::MicroserviceAuthorization,
+    models::{
+        file::{serve_file, FileMeta},
+        user::AuthorizedUser,
+    },
+};
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route(ListProjects::partial_path(), get(list_projects))
+        .route(
+            ListPublicProjects::partial_path(),
+            get(list_public_projects),
+        )
+        .route(
+            ListTelegramProjects::partial_path(),
+            get(list_telegram_projects),
+        )
+        .route(GetProject::partial_path(), get(get_project))
         .route(CreateProject::partial_path(), put(create_project))
+        .route(UpdateProject::partial_path(), patch(update_project))
         .route(DeleteProject::partial_path(), delete(delete_project))
+        .route(UploadSource::partial_path(), put(upload_source))
+        .route(ListSources::partial_path(), get(list_sources))
+        .route(DownloadSource::partial_path(), get(download_source))
+        .route(GetBuild::partial_path(), get(get_build))
+        .route(StartBuild::partial_path(), post(start_build))
+        .route(GetBuildLog::partial_path(), get(get_build_log))
+        .route(GetProjectPdf::partial_path(), get(get_project_pdf))
+        .route(GetSourcePdf::partial_path(), get(get_source_pdf))
+        .route(ListMembers::partial_path(), get(list_members))
+        .route(AddMember::partial_path(), put(add_member))
+        .route(RemoveMember::partial_path(), delete(remove_member))
+}
+
+struct ProjectRow {
+    id: i64,
+    ty: i64,
+    title: String,
+    descript: Option<String>,
+    author_id: i64,
+    entrypoint: Option<String>,
+    visibility: i64,
+}
+
+impl From<ProjectRow> for ProjectInfo {
+    fn from(v: ProjectRow) -> Self {
+        Self {
+            id: v.id,
+            ty: ProjectTy::from_bits(v.ty),
+            title: v.title,
+            description: v.descript,
+            author_id: v.author_id,
+            entrypoint: v.entrypoint,
+            visibility: ProjectVisibility::from_bits(v.visibility),
+        }
+    }
+}
+
+async fn fetch_project(id: i64, db: &Pool<Sqlite>) -> Option<ProjectRow> {
+    sqlx::query_as!(
+        ProjectRow,
+        "select id, ty, title, descript, author_id, entrypoint, visibility from project where id = ?",
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select project")
+}
+
+/// Validates fields of project set by user
+fn validate_project(title: &str, entrypoint: Option<&str>) -> Result<(), &'static str> {
+    if !check_project_title(title) {
+        return Err("lenght of `title` should be in range 2..=40");
+    }
+    if !entrypoint.map(check_entrypoint).unwrap_or(true) {
+        return Err("`entrypoint` should be relative path inside of sources");
+    }
+    Ok(())
+}
+
+/// Returns role of user in project, `None` if user is not a member
+async fn member_role(id: i64, user_id: i64, db: &Pool<Sqlite>) -> Option<ProjectRole> {
+    sqlx::query!(
+        "select role from project_member where project_id = ? and user_id = ?",
+        id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select project member")
+    .map(|v| ProjectRole::from_bits(v.role))
+}
+
+/// Checks if member is the only owner of project
+async fn is_last_owner(id: i64, user_id: i64, db: &Pool<Sqlite>) -> bool {
+    let owner = ProjectRole::Owner as i64;
+    sqlx::query!(
+        "select user_id from project_member where project_id = ? and role = ?",
+        id,
+        owner
+    )
+    .fetch_all(db)
+    .await
+    .expect("select project owners")
+    .iter()
+    .all(|v| v.user_id == user_id)
+}
+
+/// Checks that project can be viewed by user (or anonymous reader)
+async fn require_visible(
+    id: i64,
+    auth: Option<&AuthorizedUser>,
+    db: &Pool<Sqlite>,
+) -> Result<ProjectRow, api::Error> {
+    let user = match auth {
+        Some(AuthorizedUser { user, token }) => {
+            token.scope.require(UserTokenScope::READ_PROJECTS)?;
+            Some(user)
+        }
+        None => None,
+    };
+    let project = fetch_project(id, db).await.ok_or(api::Error::NotFound)?;
+
+    match (ProjectVisibility::from_bits(project.visibility), user) {
+        (ProjectVisibility::Public | ProjectVisibility::Unlisted, _) => Ok(project),
+        (ProjectVisibility::Private, Some(user)) => match member_role(id, user.id, db).await {
+            Some(_) => Ok(project),
+            None => Err(api::Error::Forbidden),
+        },
+        (ProjectVisibility::Private, None) => Err(api::Error::AuthorizationRequired),
+    }
+}
+
+/// Checks that user is member of project with at least `role`
+pub async fn require_role(
+    id: i64,
+    user_id: i64,
+    role: ProjectRole,
+    db: &Pool<Sqlite>,
+) -> Result<ProjectRole, api::Error> {
+    let exists = sqlx::query!("select id from project where id = ?", id)
+        .fetch_optional(db)
+        .await
+        .expect("select project")
+        .is_some();
+    if !exists {
+        return Err(api::Error::NotFound);
+    }
+
+    match member_role(id, user_id, db).await {
+        Some(v) if v >= role => Ok(v),
+        _ => Err(api::Error::Forbidden),
+    }
+}
+
+/// Removes project with all its sources (including files).
+pub async fn purge_project(id: i64, config: &Config, db: &Pool<Sqlite>) {
+    let mut conn = db.acquire().await.expect("acquire connection");
+    delete_project_records(id, &mut conn).await;
+    remove_project_files(id, config).await;
+}
+
+/// Removes project with its sources from database, files should be removed
+/// with [`remove_project_files`] after transaction is committed.
+pub async fn delete_project_records(id: i64, conn: &mut SqliteConnection) {
+    let mut tx = conn.begin().await.expect("begin transaction");
+    webhooks::enqueue(&mut tx, id, webhook::Event::ProjectDeleted).await;
+    sqlx::query!(
+        "delete from build_job where source_id in (select id from project_source where project_id = ?)",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("delete project build jobs");
+    sqlx::query!("delete from project_member where project_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete project members");
+    sqlx::query!("delete from project_source where project_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete project sources");
+    sqlx::query!("delete from project where id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete project");
+    tx.commit().await.expect("commit project deletion");
+}
+
+/// Removes sources of deleted project from disk
+pub async fn remove_project_files(id: i64, config: &Config) {
+    match tokio::fs::remove_dir_all(sources::project_dir(config, id)).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            eprintln!("Failed to remove sources of project {id}: {e}")
+        }
+        _ => (),
+    }
+}
+
+/// Projects that user is member of, newest first
+async fn member_projects(
+    user_id: i64,
+    limit: u32,
+    offset: u32,
+    db: &Pool<Sqlite>,
+) -> Vec<ProjectInfo> {
+    sqlx::query_as!(
+        ProjectRow,
+        r#"select project.id, project.ty, project.title, project.descript, project.author_id, project.entrypoint, project.visibility
+            from project
+            join project_member on project_member.project_id = project.id
+            where project_member.user_id = ? order by project.id desc limit ? offset ?"#,
+        user_id,
+        limit,
+        offset
+    )
+    .fetch_all(db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(ProjectInfo::from)
+    .collect()
 }
 
 pub async fn list_projects(
     State(AppState { db, .. }): State<AppState>,
-    AuthorizedUser { user, .. }: AuthorizedUser,
+    AuthorizedUser { user, token }: AuthorizedUser,
     Query(ProjectListQuery { limit, skip }): Query<<ListProjects as Endpoint>::Query>,
 ) -> api::Response<<ListProjects as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::READ_PROJECTS) {
+        return api::Response::error(e);
+    }
+
     let limit = match limit {
         0 => 50,
         v @ 1..=50 => v,
         _ => return api::Response::error(api::Error::InvalidInput),
     };
-    let (start, stop) = (limit * skip, limit * skip + limit);
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
 
-    let list = sqlx::query!(
-        "select * from project where author_id = ? limit ?,?",
-        user.id,
-        start,
-        stop
+    api::Response::Success(member_projects(user.id, limit, offset, &db).await)
+}
+
+pub async fn list_telegram_projects(
+    ms: MicroserviceAuthorization,
+    State(AppState { db, .. }): State<AppState>,
+    Query(TelegramProjectListQuery {
+        telegram_id,
+        limit,
+        skip,
+    }): Query<<ListTelegramProjects as Endpoint>::Query>,
+) -> api::Response<<ListTelegramProjects as Endpoint>::Response> {
+    if !ms.is(microservice::TELEGRAM) {
+        return api::Response::error(api::Error::AuthorizationRequired);
+    }
+
+    let limit = match limit {
+        0 => 50,
+        v @ 1..=50 => v,
+        _ => return api::Response::error(api::Error::InvalidInput),
+    };
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
+
+    let user = sqlx::query!(
+        "select id, suspended_at from user where telegram_id = ?",
+        telegram_id
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("select user by telegram_id");
+    let Some(user) = user else {
+        return api::Response::error(api::Error::NotFound);
+    };
+    if user.suspended_at.is_some() {
+        return api::Response::error(api::Error::Suspended);
+    }
+
+    api::Response::Success(member_projects(user.id, limit, offset, &db).await)
+}
+
+pub async fn list_public_projects(
+    State(AppState { db, .. }): State<AppState>,
+    Query(ProjectListQuery { limit, skip }): Query<<ListPublicProjects as Endpoint>::Query>,
+) -> api::Response<<ListPublicProjects as Endpoint>::Response> {
+    let limit = match limit {
+        0 => 50,
+        v @ 1..=50 => v,
+        _ => return api::Response::error(api::Error::InvalidInput),
+    };
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
+    let public = ProjectVisibility::Public as i64;
+
+    let list = sqlx::query_as!(
+        ProjectRow,
+        "select id, ty, title, descript, author_id, entrypoint, visibility from project where visibility = ? order by id desc limit ? offset ?",
+        public,
+        limit,
+        offset
     )
     .fetch_all(&db)
     .await
     .unwrap_or_default()
     .into_iter()
-    .map(|v| ProjectInfo {
-        id: v.id,
-        ty: ProjectTy::from_bits(v.ty),
-        title: v.title,
-        description: v.descript,
-        author_id: v.author_id,
-    })
+    .map(ProjectInfo::from)
     .collect();
 
     api::Response::Success(list)
 }
 
+pub async fn get_project(
+    State(AppState { db, .. }): State<AppState>,
+    auth: Option<AuthorizedUser>,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+) -> api::Response<<GetProject as Endpoint>::Response> {
+    match require_visible(id, auth.as_ref(), &db).await {
+        Ok(v) => api::Response::Success(v.into()),
+        Err(e) => api::Response::error(e),
+    }
+}
+
 pub async fn create_project(
     State(AppState { db, .. }): State<AppState>,
-    AuthorizedUser { user, .. }: AuthorizedUser,
-    Json(CreateProjectBody { title, description }): Json<<CreateProject as Endpoint>::Body>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(CreateProjectBody {
+        title,
+        description,
+        ty,
+        entrypoint,
+        visibility,
+    }): Json<<CreateProject as Endpoint>::Body>,
 ) -> api::Response<<CreateProject as Endpoint>::Response, &'static str> {
-    let ty = ProjectTy::Legacy;
-    let ity = ty as i64;
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROJECTS) {
+        return api::Response::error(e);
+    }
 
-    if !matches!(title.len(), 2..=40) {
-        return api::Response::error_description(
-            api::Error::InvalidInput,
-            "lenght of `title` should be in range 2..=40",
-        );
+    let (ity, ivisibility) = (ty as i64, visibility as i64);
+
+    if let Err(e) = validate_project(&title, entrypoint.as_deref()) {
+        return api::Response::error_description(api::Error::InvalidInput, e);
     }
 
     let id = sqlx::query!(
-        "insert into project(ty,title,descript,author_id) values(?,?,?,?)",
+        "insert into project(ty,title,descript,author_id,entrypoint,visibility) values(?,?,?,?,?,?)",
         ity,
         title,
         description,
-        user.id
+        user.id,
+        entrypoint,
+        ivisibility
     )
     .execute(&db)
     .await
     .map(|v| v.last_insert_rowid());
 
-    match id {
-        Ok(id) => api::Response::Success(ProjectInfo {
-            id,
-            ty,
-            title,
-            description,
-            author_id: user.id,
-        }),
-        Err(_) => api::Response::error(api::Error::Conflict),
+    let Ok(id) = id else {
+        return api::Response::error(api::Error::Conflict);
+    };
+
+    let owner = ProjectRole::Owner as i64;
+    let added_at = timestamp();
+    sqlx::query!(
+        "insert into project_member(project_id,user_id,role,added_at) values(?,?,?,?)",
+        id,
+        user.id,
+        owner,
+        added_at
+    )
+    .execute(&db)
+    .await
+    .expect("insert project owner");
+
+    api::Response::Success(ProjectInfo {
+        id,
+        ty,
+        title,
+        description,
+        author_id: user.id,
+        entrypoint,
+        visibility,
+    })
+}
+
+pub async fn update_project(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Json(UpdateProjectBody {
+        title,
+        description,
+        entrypoint,
+        visibility,
+    }): Json<<UpdateProject as Endpoint>::Body>,
+) -> api::Response<<UpdateProject as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    let role = match require_role(id, user.id, ProjectRole::Editor, &db).await {
+        Ok(v) => v,
+        Err(e) => return api::Response::error(e),
+    };
+    if visibility.is_some() && role < ProjectRole::Owner {
+        return api::Response::error(api::Error::Forbidden);
+    }
+    let Some(mut project) = fetch_project(id, &db).await else {
+        return api::Response::error(api::Error::NotFound);
+    };
+
+    if let Some(title) = title {
+        project.title = title;
+    }
+    if let Some(description) = description {
+        project.descript = description;
+    }
+    if let Some(entrypoint) = entrypoint {
+        project.entrypoint = entrypoint;
+    }
+    let published = visibility == Some(ProjectVisibility::Public)
+        && project.visibility != ProjectVisibility::Public as i64;
+    if let Some(visibility) = visibility {
+        project.visibility = visibility as i64;
+    }
+
+    if let Err(e) = validate_project(&project.title, project.entrypoint.as_deref()) {
+        return api::Response::error_description(api::Error::InvalidInput, e);
     }
+
+    sqlx::query!(
+        "update project set title = ?, descript = ?, entrypoint = ?, visibility = ? where id = ?",
+        project.title,
+        project.descript,
+        project.entrypoint,
+        project.visibility,
+        id
+    )
+    .execute(&db)
+    .await
+    .expect("update project");
+
+    if published {
+        let event = webhook::Event::ProjectPublished {
+            title: project.title.clone(),
+        };
+        let mut conn = db.acquire().await.expect("acquire connection");
+        webhooks::enqueue(&mut conn, id, event).await;
+    }
+
+    api::Response::Success(project.into())
 }
 
 pub async fn delete_project(
-    AuthorizedUser { user, .. }: AuthorizedUser,
+    AuthorizedUser { user, token }: AuthorizedUser,
     Path(ProjectPath { id }): Path<ProjectPath>,
-    State(AppState { db, .. }): State<AppState>,
+    State(AppState { db, config, .. }): State<AppState>,
 ) -> api::Response {
-    let res = sqlx::query!(
-        "delete from project where id = ? and author_id = ?",
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Owner, &db).await {
+        return api::Response::error(e);
+    }
+
+    purge_project(id, config, &db).await;
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn upload_source(
+    State(AppState {
+        db, config, builds, ..
+    }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Query(UploadSourceQuery { format }): Query<<UploadSource as Endpoint>::Query>,
+    body: Body,
+) -> api::Response<<UploadSource as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::UPLOAD_SOURCES) {
+        return api::Response::error(e);
+    }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Editor, &db).await {
+        return api::Response::error(e);
+    }
+
+    let Ok(data) = axum::body::to_bytes(body, config.max_source_size).await else {
+        return api::Response::error_description(api::Error::InvalidInput, "archive is too large");
+    };
+
+    let valid = {
+        let data = data.clone();
+        let limits = sources::UnpackLimits::new(config);
+        tokio::task::spawn_blocking(move || sources::validate_archive(format, &data, limits))
+            .await
+            .expect("validate source archive")
+    };
+    if let Err(e) = valid {
+        return api::Response::error_description(api::Error::InvalidInput, e);
+    }
+
+    let created_at = timestamp();
+    let iformat = format as i64;
+    let size = data.len() as i64;
+
+    let source_id = sqlx::query!(
+        "insert into project_source(project_id,created_at,format,size,uploader_id) values (?,?,?,?,?)",
         id,
+        created_at,
+        iformat,
+        size,
         user.id
     )
     .execute(&db)
     .await
-    .map(|v| v.rows_affected())
-    .unwrap_or_default();
+    .expect("insert project source")
+    .last_insert_rowid();
+
+    let path = sources::archive_path(config, id, source_id, format);
+    let res = match path.parent() {
+        Some(dir) => tokio::fs::create_dir_all(dir).await,
+        None => Ok(()),
+    };
+    if let Err(e) = res.and(tokio::fs::write(&path, &data).await) {
+        eprintln!("Failed to store source {source_id} of project {id}: {e}");
+        _ = sqlx::query!("delete from project_source where id = ?", source_id)
+            .execute(&db)
+            .await;
+        return api::Response::error(api::Error::Internal);
+    }
+
+    builds.push(&db, source_id).await;
+
+    let event = webhook::Event::SourceUploaded {
+        source_id,
+        format,
+        size,
+        uploaded_by: user.username.clone(),
+    };
+    let mut conn = db.acquire().await.expect("acquire connection");
+    webhooks::enqueue(&mut conn, id, event).await;
+
+    api::Response::Success(SourceInfo {
+        id: source_id,
+        project_id: id,
+        created_at,
+        format,
+        size,
+        uploader_id: Some(user.id),
+        build_status: BuildStatus::Queued,
+    })
+}
+
+pub async fn list_sources(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Query(ProjectListQuery { limit, skip }): Query<<ListSources as Endpoint>::Query>,
+) -> api::Response<<ListSources as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::READ_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Viewer, &db).await {
+        return api::Response::error(e);
+    }
+
+    let limit = match limit {
+        0 => 50,
+        v @ 1..=50 => v,
+        _ => return api::Response::error(api::Error::InvalidInput),
+    };
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
+
+    let list = sqlx::query!(
+        "select * from project_source where project_id = ? order by id desc limit ? offset ?",
+        id,
+        limit,
+        offset
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|v| SourceInfo {
+        id: v.id,
+        project_id: v.project_id,
+        created_at: v.created_at,
+        format: SourceFormat::from_bits(v.format),
+        size: v.size,
+        uploader_id: v.uploader_id,
+        build_status: BuildStatus::from_bits(v.build_status),
+    })
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn download_source(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(SourcePath { id, source_id }): Path<SourcePath>,
+) -> Result<impl IntoResponse, api::EmptyResponse> {
+    token
+        .scope
+        .require(UserTokenScope::READ_PROJECTS)
+        .map_err(api::EmptyResponse::error)?;
+
+    require_role(id, user.id, ProjectRole::Viewer, &db)
+        .await
+        .map_err(api::EmptyResponse::error)?;
+
+    let format = sqlx::query!(
+        "select format from project_source where id = ? and project_id = ?",
+        source_id,
+        id
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("select project source")
+    .map(|v| SourceFormat::from_bits(v.format))
+    .ok_or(api::EmptyResponse::error(api::Error::NotFound))?;
+
+    let data = tokio::fs::read(sources::archive_path(config, id, source_id, format))
+        .await
+        .map_err(|_| api::EmptyResponse::error(api::Error::NotFound))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.mime().to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{id}-{source_id}.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        data,
+    ))
+}
 
-    if res == 0 {
-        api::Response::error(api::Error::Forbidden)
-    } else {
-        api::Response::Success(api::EmptyErrorData)
+/// Returns build information of source revision in project
+async fn fetch_build(id: i64, source_id: i64, db: &Pool<Sqlite>) -> Result<BuildInfo, api::Error> {
+    sqlx::query!(
+        "select build_status, build_started_at, build_finished_at, build_exit_code from project_source where id = ? and project_id = ?",
+        source_id,
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select project source build")
+    .map(|v| BuildInfo {
+        source_id,
+        status: BuildStatus::from_bits(v.build_status),
+        started_at: v.build_started_at,
+        finished_at: v.build_finished_at,
+        exit_code: v.build_exit_code,
+    })
+    .ok_or(api::Error::NotFound)
+}
+
+pub async fn get_build(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(SourcePath { id, source_id }): Path<SourcePath>,
+) -> api::Response<<GetBuild as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::READ_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Viewer, &db).await {
+        return api::Response::error(e);
+    }
+
+    match fetch_build(id, source_id, &db).await {
+        Ok(v) => api::Response::Success(v),
+        Err(e) => api::Response::error(e),
+    }
+}
+
+pub async fn start_build(
+    State(AppState { db, builds, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(SourcePath { id, source_id }): Path<SourcePath>,
+) -> api::Response<<StartBuild as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::TRIGGER_BUILDS) {
+        return api::Response::error(e);
     }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Editor, &db).await {
+        return api::Response::error(e);
+    }
+
+    match fetch_build(id, source_id, &db).await {
+        Ok(BuildInfo {
+            status: BuildStatus::Queued | BuildStatus::Running,
+            ..
+        }) => return api::Response::error(api::Error::Conflict),
+        Ok(_) => (),
+        Err(e) => return api::Response::error(e),
+    }
+
+    builds.push(&db, source_id).await;
+
+    match fetch_build(id, source_id, &db).await {
+        Ok(v) => api::Response::Success(v),
+        Err(e) => api::Response::error(e),
+    }
+}
+
+pub async fn get_build_log(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(SourcePath { id, source_id }): Path<SourcePath>,
+) -> Result<impl IntoResponse, api::EmptyResponse> {
+    token
+        .scope
+        .require(UserTokenScope::READ_PROJECTS)
+        .map_err(api::EmptyResponse::error)?;
+
+    require_role(id, user.id, ProjectRole::Viewer, &db)
+        .await
+        .map_err(api::EmptyResponse::error)?;
+
+    let log = tokio::fs::read(sources::log_path(config, id, source_id))
+        .await
+        .map_err(|_| api::EmptyResponse::error(api::Error::NotFound))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        String::from_utf8_lossy(&log).into_owned(),
+    ))
+}
+
+/// Serves PDF of successfully built source revision
+async fn serve_pdf(
+    ProjectRow { id, title, .. }: ProjectRow,
+    source_id: Option<i64>,
+    headers: &HeaderMap,
+    config: &Config,
+    db: &Pool<Sqlite>,
+) -> Result<Response, api::EmptyResponse> {
+    let not_found = || api::EmptyResponse::error(api::Error::NotFound);
+    let succeeded = BuildStatus::Succeeded as i64;
+
+    let (source_id, finished_at) = sqlx::query!(
+        r#"select id, build_finished_at from project_source
+            where project_id = ? and build_status = ? and (? is null or id = ?)
+            order by id desc limit 1"#,
+        id,
+        succeeded,
+        source_id,
+        source_id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select built project source")
+    .map(|v| (v.id, v.build_finished_at.unwrap_or_default()))
+    .ok_or_else(not_found)?;
+
+    serve_file(
+        &sources::pdf_path(config, id, source_id),
+        FileMeta {
+            content_type: "application/pdf",
+            file_name: &format!("{title}.pdf"),
+            etag: format!("\"{source_id}-{finished_at}\""),
+            last_modified: finished_at,
+        },
+        headers,
+    )
+    .await
+}
+
+pub async fn get_project_pdf(
+    State(AppState { db, config, .. }): State<AppState>,
+    auth: Option<AuthorizedUser>,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    headers: HeaderMap,
+) -> Result<Response, api::EmptyResponse> {
+    let project = require_visible(id, auth.as_ref(), &db)
+        .await
+        .map_err(api::EmptyResponse::error)?;
+
+    serve_pdf(project, None, &headers, config, &db).await
+}
+
+pub async fn get_source_pdf(
+    State(AppState { db, config, .. }): State<AppState>,
+    auth: Option<AuthorizedUser>,
+    Path(SourcePath { id, source_id }): Path<SourcePath>,
+    headers: HeaderMap,
+) -> Result<Response, api::EmptyResponse> {
+    let project = require_visible(id, auth.as_ref(), &db)
+        .await
+        .map_err(api::EmptyResponse::error)?;
+
+    serve_pdf(project, Some(source_id), &headers, config, &db).await
+}
+
+pub async fn list_members(
+    State(AppState { db, .. }): State<AppState>,
+    auth: Option<AuthorizedUser>,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+) -> api::Response<<ListMembers as Endpoint>::Response> {
+    if let Err(e) = require_visible(id, auth.as_ref(), &db).await {
+        return api::Response::error(e);
+    }
+
+    let list = sqlx::query!(
+        r#"select project_member.user_id, user.username, project_member.role, project_member.added_at
+            from project_member
+            join user on project_member.user_id = user.id
+            where project_member.project_id = ? order by project_member.role desc, project_member.added_at"#,
+        id
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|v| MemberInfo {
+        user_id: v.user_id,
+        username: v.username,
+        role: ProjectRole::from_bits(v.role),
+        added_at: v.added_at,
+    })
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn add_member(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Json(AddMemberBody { username, role }): Json<<AddMember as Endpoint>::Body>,
+) -> api::Response<<AddMember as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    if let Err(e) = require_role(id, user.id, ProjectRole::Owner, &db).await {
+        return api::Response::error(e);
+    }
+
+    let Some(user_id) = sqlx::query!("select id from user where username = ?", username)
+        .fetch_optional(&db)
+        .await
+        .expect("select user by username")
+        .map(|v| v.id)
+    else {
+        return api::Response::error(api::Error::NotFound);
+    };
+
+    let previous = member_role(id, user_id, &db).await;
+    if role < ProjectRole::Owner
+        && previous == Some(ProjectRole::Owner)
+        && is_last_owner(id, user_id, &db).await
+    {
+        return api::Response::error_description(
+            api::Error::Conflict,
+            "project should have at least one owner",
+        );
+    }
+
+    let irole = role as i64;
+    let now = timestamp();
+    let added_at = sqlx::query!(
+        r#"insert into project_member(project_id,user_id,role,added_at) values(?,?,?,?)
+            on conflict(project_id,user_id) do update set role = excluded.role
+            returning added_at"#,
+        id,
+        user_id,
+        irole,
+        now
+    )
+    .fetch_one(&db)
+    .await
+    .expect("upsert project member")
+    .added_at;
+
+    if previous.is_none() && user_id != user.id {
+        let project_title = sqlx::query!("select title from project where id = ?", id)
+            .fetch_one(&db)
+            .await
+            .expect("select project title")
+            .title;
+        let event = Event::MemberAdded {
+            project_id: id,
+            project_title,
+            role,
+            added_by: user.username,
+        };
+        notifications::enqueue(config, &db, user_id, event).await;
+    }
+
+    api::Response::Success(MemberInfo {
+        user_id,
+        username,
+        role,
+        added_at,
+    })
+}
+
+pub async fn remove_member(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(MemberPath { id, username }): Path<MemberPath>,
+) -> api::Response<api::EmptyErrorData, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROJECTS) {
+        return api::Response::error(e);
+    }
+
+    let user_id = sqlx::query!("select id from user where username = ?", username)
+        .fetch_optional(&db)
+        .await
+        .expect("select user by username")
+        .map(|v| v.id);
+
+    // Any member can leave project, others are removed by owners
+    let required = match user_id {
+        Some(v) if v == user.id => ProjectRole::Viewer,
+        _ => ProjectRole::Owner,
+    };
+    if let Err(e) = require_role(id, user.id, required, &db).await {
+        return api::Response::error(e);
+    }
+
+    let Some(user_id) = user_id else {
+        return api::Response::error(api::Error::NotFound);
+    };
+    match member_role(id, user_id, &db).await {
+        None => return api::Response::error(api::Error::NotFound),
+        Some(ProjectRole::Owner) if is_last_owner(id, user_id, &db).await => {
+            return api::Response::error_description(
+                api::Error::Conflict,
+                "project should have at least one owner",
+            )
+        }
+        Some(_) => (),
+    }
+
+    sqlx::query!(
+        "delete from project_member where project_id = ? and user_id = ?",
+        id,
+        user_id
+    )
+    .execute(&db)
+    .await
+    .expect("delete project member");
+
+    api::Response::Success(api::EmptyErrorData)
 }
@@ -1,25 +1,621 @@
-use axum::{routing::get, Router};
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, patch, put},
+    Json, Router,
+};
 use dp_core::v1::{
     api,
     endpoint::{
-        user::{GetSelf, SelfUser},
+        user::{
+            CreateToken, CreateTokenBody, DeleteSelf, DeleteSelfBody, GetNotificationSettings,
+            GetSelf, GetUser, ListTokens, NotificationSettings, PublicUser, RevokeAllTokens,
+            RevokeToken, RevokeTokensQuery, SelfUser, TokenInfo, TokenPath,
+            UpdateNotificationSettings, UpdateSelf, UpdateSelfBody, UsernamePath,
+        },
         Endpoint,
     },
+    notification::NotificationKinds,
+    project::ProjectRole,
+    user::{
+        check_avatar, check_orcid, check_username, UserProfile, UserTokenScope, UserTokenTy, UserTy,
+    },
 };
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection};
 
-use crate::routes::AppState;
+use crate::{notifications, routes::AppState, timestamp};
 
-use super::models::user::AuthorizedUser;
+use super::{
+    auth::{issue_scoped_token, revoke_family, TokenParams},
+    models::user::AuthorizedUser,
+    projects::{delete_project_records, remove_project_files},
+};
 
 pub fn get_routes() -> Router<AppState> {
-    Router::new().route(GetSelf::partial_path(), get(get_self))
+    Router::new()
+        .route(GetSelf::partial_path(), get(get_self))
+        .route(UpdateSelf::partial_path(), patch(update_self))
+        .route(DeleteSelf::partial_path(), delete(delete_self))
+        .route(GetUser::partial_path(), get(get_user))
+        .route(
+            GetNotificationSettings::partial_path(),
+            get(get_notification_settings),
+        )
+        .route(
+            UpdateNotificationSettings::partial_path(),
+            put(update_notification_settings),
+        )
+        .route(ListTokens::partial_path(), get(list_tokens))
+        .route(CreateToken::partial_path(), put(create_token))
+        .route(RevokeToken::partial_path(), delete(revoke_token))
+        .route(RevokeAllTokens::partial_path(), delete(revoke_all_tokens))
+}
+
+struct ProfileRow {
+    id: i64,
+    username: String,
+    display_name: Option<String>,
+    affiliation: Option<String>,
+    orcid: Option<String>,
+    bio: Option<String>,
+    avatar: Option<String>,
+}
+
+impl From<ProfileRow> for PublicUser {
+    fn from(v: ProfileRow) -> Self {
+        Self {
+            id: v.id,
+            username: v.username,
+            profile: UserProfile {
+                display_name: v.display_name,
+                affiliation: v.affiliation,
+                orcid: v.orcid,
+                bio: v.bio,
+                avatar: v.avatar,
+            },
+        }
+    }
+}
+
+fn validate_profile(profile: &UserProfile) -> Result<(), &'static str> {
+    let len = |v: &Option<String>| v.as_deref().map(|v| v.chars().count());
+    if len(&profile.display_name).is_some_and(|v| !matches!(v, 1..=64)) {
+        return Err("length of `display_name` should be in range 1..=64");
+    }
+    if len(&profile.affiliation).is_some_and(|v| !matches!(v, 1..=128)) {
+        return Err("length of `affiliation` should be in range 1..=128");
+    }
+    if len(&profile.bio).is_some_and(|v| v > 1024) {
+        return Err("length of `bio` should not exceed 1024");
+    }
+    if profile.orcid.as_deref().is_some_and(|v| !check_orcid(v)) {
+        return Err("`orcid` should be valid ORCID iD like 0000-0002-1825-0097");
+    }
+    if profile.avatar.as_deref().is_some_and(|v| !check_avatar(v)) {
+        return Err("`avatar` should be HTTPS URL");
+    }
+    Ok(())
+}
+
+async fn fetch_profile(id: i64, db: &Pool<Sqlite>) -> Option<PublicUser> {
+    sqlx::query_as!(
+        ProfileRow,
+        "select id, username, display_name, affiliation, orcid, bio, avatar from user where id = ?",
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select user profile")
+    .map(PublicUser::from)
+}
+
+/// Checks if username is used by user other than `user_id` now or was used
+/// before
+pub async fn is_username_taken(username: &str, user_id: Option<i64>, db: &Pool<Sqlite>) -> bool {
+    sqlx::query!(
+        r#"select user_id as "user_id!: i64" from username_history where username = ? and user_id is not ?
+            union all
+            select id from user where username = ? and id is not ?"#,
+        username,
+        user_id,
+        username,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select username")
+    .is_some()
+}
+
+/// Revokes all tokens of user, including refresh tokens
+pub async fn revoke_tokens(user_id: i64, conn: &mut SqliteConnection) {
+    let mut tx = conn.begin().await.expect("begin transaction");
+    sqlx::query!("delete from usertoken where user_id = ?", user_id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete user tokens");
+    sqlx::query!("delete from refresh_token where user_id = ?", user_id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete refresh tokens");
+    tx.commit().await.expect("commit token revocation");
+}
+
+/// Deletes user with tokens and memberships, references to user from
+/// sources and invites are cleared. Projects authored by user should be
+/// deleted or transferred before.
+pub async fn delete_user(id: i64, conn: &mut SqliteConnection) {
+    let mut tx = conn.begin().await.expect("begin transaction");
+    revoke_tokens(id, &mut tx).await;
+    sqlx::query!("delete from project_member where user_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete project memberships");
+    sqlx::query!(
+        "update project_source set uploader_id = null where uploader_id = ?",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("forget uploader");
+    sqlx::query!(
+        "update userinvite set claimed_user_id = null where claimed_user_id = ?",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("forget invite claim");
+    sqlx::query!(
+        "update userinvite set created_by = null where created_by = ?",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .expect("forget invite creator");
+    sqlx::query!("delete from user_identity where user_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete linked identities");
+    sqlx::query!("delete from username_history where user_id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete username history");
+    sqlx::query!("delete from user where id = ?", id)
+        .execute(&mut *tx)
+        .await
+        .expect("delete user");
+    tx.commit().await.expect("commit user deletion");
 }
 
 pub async fn get_self(
     AuthorizedUser { user, token }: AuthorizedUser,
 ) -> api::Response<<GetSelf as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::READ_PROFILE) {
+        return api::Response::error(e);
+    }
+
     api::Response::Success(SelfUser {
         user,
-        expires_at: token.issued_at + token.ty.lifetime(),
+        expires_at: token.expires_at,
     })
 }
+
+pub async fn get_user(
+    State(AppState { db, .. }): State<AppState>,
+    Path(UsernamePath { username }): Path<UsernamePath>,
+) -> api::Response<<GetUser as Endpoint>::Response> {
+    let id = sqlx::query!(
+        r#"select id as "id!: i64" from user where username = ?
+            union all
+            select user_id from username_history where username = ?"#,
+        username,
+        username
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("select user by username")
+    .map(|v| v.id);
+
+    match id {
+        Some(id) => match fetch_profile(id, &db).await {
+            Some(v) => api::Response::Success(v),
+            None => api::Response::error(api::Error::NotFound),
+        },
+        None => api::Response::error(api::Error::NotFound),
+    }
+}
+
+pub async fn update_self(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(UpdateSelfBody {
+        username,
+        display_name,
+        affiliation,
+        orcid,
+        bio,
+        avatar,
+    }): Json<<UpdateSelf as Endpoint>::Body>,
+) -> api::Response<<UpdateSelf as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROFILE) {
+        return api::Response::error(e);
+    }
+    let Some(mut current) = fetch_profile(user.id, &db).await else {
+        return api::Response::error(api::Error::NotFound);
+    };
+
+    let profile = &mut current.profile;
+    if let Some(display_name) = display_name {
+        profile.display_name = display_name;
+    }
+    if let Some(affiliation) = affiliation {
+        profile.affiliation = affiliation;
+    }
+    if let Some(orcid) = orcid {
+        profile.orcid = orcid;
+    }
+    if let Some(bio) = bio {
+        profile.bio = bio;
+    }
+    if let Some(avatar) = avatar {
+        profile.avatar = avatar;
+    }
+    if let Err(e) = validate_profile(profile) {
+        return api::Response::error_description(api::Error::InvalidInput, e);
+    }
+
+    let username = username.filter(|v| *v != current.username);
+    if let Some(username) = &username {
+        if !check_username(username) {
+            return api::Response::error_description(
+                api::Error::InvalidInput,
+                "`username` may contain only latin letters, digits, `.` and `-`",
+            );
+        }
+        if is_username_taken(username, Some(user.id), &db).await {
+            return api::Response::error(api::Error::Conflict);
+        }
+    }
+
+    let new_username = username.as_ref().unwrap_or(&current.username);
+    let res = sqlx::query!(
+        "update user set username = ?, display_name = ?, affiliation = ?, orcid = ?, bio = ?, avatar = ? where id = ?",
+        new_username,
+        profile.display_name,
+        profile.affiliation,
+        profile.orcid,
+        profile.bio,
+        profile.avatar,
+        user.id
+    )
+    .execute(&db)
+    .await;
+    if res.is_err() {
+        return api::Response::error(api::Error::Conflict);
+    }
+
+    if let Some(username) = username {
+        // User may return to one of previous usernames
+        sqlx::query!(
+            "delete from username_history where username = ? and user_id = ?",
+            username,
+            user.id
+        )
+        .execute(&db)
+        .await
+        .expect("delete username from history");
+        let changed_at = timestamp();
+        sqlx::query!(
+            "insert into username_history(username, user_id, changed_at) values (?,?,?)",
+            current.username,
+            user.id,
+            changed_at
+        )
+        .execute(&db)
+        .await
+        .expect("insert username history");
+        current.username = username;
+    }
+
+    api::Response::Success(current)
+}
+
+pub async fn delete_self(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(DeleteSelfBody { transfer_to }): Json<<DeleteSelf as Endpoint>::Body>,
+) -> api::Response<api::EmptyErrorData, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+    if matches!(token.ty, UserTokenTy::Personal) {
+        return api::Response::error_description(
+            api::Error::NoAccess,
+            "account can be deleted only with session token",
+        );
+    }
+
+    let receiver = match transfer_to {
+        Some(username) => {
+            let normal = UserTy::Normal as i64;
+            let receiver = sqlx::query!(
+                "select id from user where username = ? and ty = ? and suspended_at is null",
+                username,
+                normal
+            )
+            .fetch_optional(&db)
+            .await
+            .expect("select receiver of projects");
+            match receiver {
+                Some(v) if v.id != user.id => Some(v.id),
+                Some(_) => {
+                    return api::Response::error_description(
+                        api::Error::InvalidInput,
+                        "projects can't be transferred to deleted user",
+                    )
+                }
+                None => return api::Response::error(api::Error::NotFound),
+            }
+        }
+        None => None,
+    };
+
+    // Files of deleted projects are removed after transaction is committed
+    let mut tx = db.begin().await.expect("begin transaction");
+    let mut deleted = Vec::new();
+
+    let owner = ProjectRole::Owner as i64;
+    let projects = sqlx::query!(
+        r#"select id, author_id,
+            (select user_id from project_member
+                where project_id = project.id and role = ? and user_id != ?
+                order by added_at limit 1) as "other_owner?: i64"
+            from project
+            where author_id = ? or id in (select project_id from project_member where user_id = ? and role = ?)"#,
+        owner,
+        user.id,
+        user.id,
+        user.id,
+        owner
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .expect("select owned projects");
+
+    for project in projects {
+        let author_id = match (project.other_owner, receiver) {
+            (Some(other), _) => other,
+            (None, Some(receiver)) => {
+                let added_at = timestamp();
+                sqlx::query!(
+                    r#"insert into project_member(project_id, user_id, role, added_at) values (?,?,?,?)
+                        on conflict(project_id, user_id) do update set role = excluded.role"#,
+                    project.id,
+                    receiver,
+                    owner,
+                    added_at
+                )
+                .execute(&mut *tx)
+                .await
+                .expect("transfer project");
+                receiver
+            }
+            (None, None) => {
+                delete_project_records(project.id, &mut tx).await;
+                deleted.push(project.id);
+                continue;
+            }
+        };
+
+        if project.author_id == user.id {
+            sqlx::query!(
+                "update project set author_id = ? where id = ?",
+                author_id,
+                project.id
+            )
+            .execute(&mut *tx)
+            .await
+            .expect("change project author");
+        }
+    }
+
+    if let Some(telegram_id) = user.telegram_id {
+        notifications::discard(&mut *tx, telegram_id).await;
+    }
+    delete_user(user.id, &mut tx).await;
+    tx.commit().await.expect("commit account deletion");
+
+    for id in deleted {
+        remove_project_files(id, config).await;
+    }
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn get_notification_settings(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+) -> api::Response<<GetNotificationSettings as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::READ_PROFILE) {
+        return api::Response::error(e);
+    }
+
+    let enabled = sqlx::query!("select notifications from user where id = ?", user.id)
+        .fetch_one(&db)
+        .await
+        .expect("select notification settings")
+        .notifications;
+
+    api::Response::Success(NotificationSettings {
+        enabled: NotificationKinds::from_bits_retain(enabled),
+    })
+}
+
+pub async fn update_notification_settings(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(NotificationSettings { enabled }): Json<<UpdateNotificationSettings as Endpoint>::Body>,
+) -> api::Response<<UpdateNotificationSettings as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::WRITE_PROFILE) {
+        return api::Response::error(e);
+    }
+
+    let enabled = NotificationKinds::from_bits_truncate(enabled.bits());
+    let bits = enabled.bits();
+    sqlx::query!(
+        "update user set notifications = ? where id = ?",
+        bits,
+        user.id
+    )
+    .execute(&db)
+    .await
+    .expect("update notification settings");
+
+    api::Response::Success(NotificationSettings { enabled })
+}
+
+pub async fn list_tokens(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+) -> api::Response<<ListTokens as Endpoint>::Response> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+
+    let list = sqlx::query!(
+        "select id, ty, scope, label, issued_at, expires_at, last_used_at from usertoken where user_id = ? order by id desc",
+        user.id
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|v| TokenInfo {
+        id: v.id,
+        ty: UserTokenTy::from_bits(v.ty),
+        scope: UserTokenScope::from_bits_retain(v.scope),
+        label: v.label,
+        issued_at: v.issued_at,
+        expires_at: v.expires_at,
+        last_used_at: v.last_used_at,
+        current: v.id == token.id,
+    })
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn create_token(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Json(CreateTokenBody {
+        label,
+        scope,
+        lifetime,
+    }): Json<<CreateToken as Endpoint>::Body>,
+) -> api::Response<<CreateToken as Endpoint>::Response, &'static str> {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+    if let Err(e) = token.scope.require(scope) {
+        return api::Response::error_description(e, "token can't have more scopes than creator");
+    }
+
+    if !matches!(label.chars().count(), 1..=64) {
+        return api::Response::error_description(
+            api::Error::InvalidInput,
+            "lenght of `label` should be in range 1..=64",
+        );
+    }
+    let max_lifetime = config.token.lifetime(UserTokenTy::Personal);
+    let lifetime = match lifetime {
+        None => max_lifetime,
+        Some(v @ 1..) if v <= max_lifetime => v,
+        Some(_) => {
+            return api::Response::error_description(
+                api::Error::InvalidInput,
+                "`lifetime` should be positive and not longer than allowed by server",
+            )
+        }
+    };
+
+    let params = TokenParams {
+        scope,
+        label: Some(&label),
+        lifetime,
+        ..TokenParams::new(UserTokenTy::Personal, config)
+    };
+    api::Response::Success(issue_scoped_token(user.id, params, config, &db).await)
+}
+
+pub async fn revoke_token(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(TokenPath { id }): Path<TokenPath>,
+) -> api::Response {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+
+    let deleted = sqlx::query!(
+        "delete from usertoken where id = ? and user_id = ? returning family",
+        id,
+        user.id
+    )
+    .fetch_optional(&db)
+    .await
+    .expect("delete user token");
+
+    match deleted {
+        Some(v) => {
+            // Session can't be continued with refresh token
+            if let Some(family) = v.family {
+                revoke_family(family, &db).await;
+            }
+            api::Response::Success(api::EmptyErrorData)
+        }
+        None => api::Response::error(api::Error::NotFound),
+    }
+}
+
+pub async fn revoke_all_tokens(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Query(RevokeTokensQuery { keep_current }): Query<<RevokeAllTokens as Endpoint>::Query>,
+) -> api::Response {
+    if let Err(e) = token.scope.require(UserTokenScope::MANAGE_TOKENS) {
+        return api::Response::error(e);
+    }
+
+    let keep = keep_current.then_some(token.id);
+    let keep_family = match keep {
+        Some(id) => {
+            sqlx::query!("select family from usertoken where id = ?", id)
+                .fetch_one(&db)
+                .await
+                .expect("select user token family")
+                .family
+        }
+        None => None,
+    };
+
+    sqlx::query!(
+        "delete from usertoken where user_id = ? and (? is null or id != ?)",
+        user.id,
+        keep,
+        keep
+    )
+    .execute(&db)
+    .await
+    .expect("delete user tokens");
+    sqlx::query!(
+        "delete from refresh_token where user_id = ? and (? is null or family != ?)",
+        user.id,
+        keep_family,
+        keep_family
+    )
+    .execute(&db)
+    .await
+    .expect("delete refresh tokens");
+
+    api::Response::Success(api::EmptyErrorData)
+}
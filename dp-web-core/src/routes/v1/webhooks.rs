@@ -0,0 +1,372 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, patch, post, put},
+    Json, Router,
+};
+use dp_core::v1::{
+    api,
+    endpoint::{
+        projects::ProjectPath,
+        webhooks::{
+            CreateWebhook, CreateWebhookBody, CreateWebhookResponse, DeleteWebhook, DeliveryInfo,
+            DeliveryListQuery, DeliveryPath, ListDeliveries, ListWebhooks, Redeliver,
+            UpdateWebhook, UpdateWebhookBody, WebhookInfo, WebhookPath,
+        },
+        Endpoint,
+    },
+    project::ProjectRole,
+    user::{User, UserToken, UserTokenScope},
+    webhook::{DeliveryStatus, WebhookEvents},
+};
+use reqwest::Url;
+use sqlx::{Pool, Sqlite};
+
+use crate::{config::WebhookConfig, routes::AppState, timestamp, webhooks};
+
+use super::{
+    models::user::{generate_token, AuthorizedUser},
+    projects::require_role,
+};
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new()
+        .route(ListWebhooks::partial_path(), get(list_webhooks))
+        .route(CreateWebhook::partial_path(), put(create_webhook))
+        .route(UpdateWebhook::partial_path(), patch(update_webhook))
+        .route(DeleteWebhook::partial_path(), delete(delete_webhook))
+        .route(ListDeliveries::partial_path(), get(list_deliveries))
+        .route(Redeliver::partial_path(), post(redeliver))
+}
+
+struct WebhookRow {
+    id: i64,
+    project_id: i64,
+    url: String,
+    events: i64,
+    created_at: i64,
+}
+
+impl From<WebhookRow> for WebhookInfo {
+    fn from(v: WebhookRow) -> Self {
+        Self {
+            id: v.id,
+            project_id: v.project_id,
+            url: v.url,
+            events: WebhookEvents::from_bits_retain(v.events),
+            created_at: v.created_at,
+        }
+    }
+}
+
+struct DeliveryRow {
+    id: i64,
+    webhook_id: i64,
+    event: String,
+    status: i64,
+    attempts: i64,
+    created_at: i64,
+    last_attempt_at: Option<i64>,
+    response_status: Option<i64>,
+    error: Option<String>,
+}
+
+impl From<DeliveryRow> for DeliveryInfo {
+    fn from(v: DeliveryRow) -> Self {
+        Self {
+            id: v.id,
+            webhook_id: v.webhook_id,
+            event: v.event,
+            status: DeliveryStatus::from_bits(v.status),
+            attempts: v.attempts,
+            created_at: v.created_at,
+            last_attempt_at: v.last_attempt_at,
+            response_status: v.response_status,
+            error: v.error,
+        }
+    }
+}
+
+/// Checks that webhooks of project can be managed with token
+async fn require_owner(
+    id: i64,
+    user: &User,
+    token: &UserToken,
+    scope: UserTokenScope,
+    db: &Pool<Sqlite>,
+) -> Result<(), api::Error> {
+    token.scope.require(scope)?;
+    require_role(id, user.id, ProjectRole::Owner, db)
+        .await
+        .map(|_| ())
+}
+
+async fn validate_url(url: &str, config: &WebhookConfig) -> Result<(), &'static str> {
+    let url = match Url::parse(url) {
+        Ok(v) if url.len() <= 2048 && matches!(v.scheme(), "http" | "https") && v.has_host() => v,
+        _ => return Err("`url` should be http or https URL not longer than 2048"),
+    };
+    if !webhooks::check_url(&url, config).await {
+        return Err("host of `url` should resolve to public address");
+    }
+    Ok(())
+}
+
+fn validate_secret(secret: &str) -> Result<(), &'static str> {
+    match secret.chars().count() {
+        16..=128 => Ok(()),
+        _ => Err("length of `secret` should be in range 16..=128"),
+    }
+}
+
+async fn fetch_webhook(
+    id: i64,
+    webhook_id: i64,
+    db: &Pool<Sqlite>,
+) -> Result<WebhookInfo, api::Error> {
+    sqlx::query_as!(
+        WebhookRow,
+        "select id, project_id, url, events, created_at from webhook where id = ? and project_id = ?",
+        webhook_id,
+        id
+    )
+    .fetch_optional(db)
+    .await
+    .expect("select webhook")
+    .map(WebhookInfo::from)
+    .ok_or(api::Error::NotFound)
+}
+
+async fn fetch_delivery(id: i64, db: &Pool<Sqlite>) -> DeliveryInfo {
+    sqlx::query_as!(
+        DeliveryRow,
+        r#"select id, webhook_id, event, status, attempts, created_at, last_attempt_at,
+            response_status, error from webhook_delivery where id = ?"#,
+        id
+    )
+    .fetch_one(db)
+    .await
+    .expect("select webhook delivery")
+    .into()
+}
+
+pub async fn list_webhooks(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+) -> api::Response<<ListWebhooks as Endpoint>::Response> {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::READ_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+
+    let list = sqlx::query_as!(
+        WebhookRow,
+        "select id, project_id, url, events, created_at from webhook where project_id = ? order by id",
+        id
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(WebhookInfo::from)
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn create_webhook(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(ProjectPath { id }): Path<ProjectPath>,
+    Json(CreateWebhookBody {
+        url,
+        events,
+        secret,
+    }): Json<<CreateWebhook as Endpoint>::Body>,
+) -> api::Response<<CreateWebhook as Endpoint>::Response, &'static str> {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::WRITE_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+
+    let secret = secret.unwrap_or_else(generate_token);
+    if let Err(e) = validate_secret(&secret) {
+        return api::Response::error_description(api::Error::InvalidInput, e);
+    }
+    if let Err(e) = validate_url(&url, &config.webhooks).await {
+        return api::Response::error_description(api::Error::InvalidInput, e);
+    }
+
+    let events = WebhookEvents::from_bits_truncate(events.bits());
+    let ievents = events.bits();
+    let created_at = timestamp();
+    let webhook_id = sqlx::query!(
+        "insert into webhook(project_id,url,secret,events,created_at) values (?,?,?,?,?)",
+        id,
+        url,
+        secret,
+        ievents,
+        created_at
+    )
+    .execute(&db)
+    .await
+    .expect("insert webhook")
+    .last_insert_rowid();
+
+    api::Response::Success(CreateWebhookResponse {
+        webhook: WebhookInfo {
+            id: webhook_id,
+            project_id: id,
+            url,
+            events,
+            created_at,
+        },
+        secret,
+    })
+}
+
+pub async fn update_webhook(
+    State(AppState { db, config, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(WebhookPath { id, webhook_id }): Path<WebhookPath>,
+    Json(UpdateWebhookBody {
+        url,
+        events,
+        secret,
+    }): Json<<UpdateWebhook as Endpoint>::Body>,
+) -> api::Response<<UpdateWebhook as Endpoint>::Response, &'static str> {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::WRITE_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_webhook(id, webhook_id, &db).await {
+        return api::Response::error(e);
+    }
+
+    if let Some(Err(e)) = secret.as_deref().map(validate_secret) {
+        return api::Response::error_description(api::Error::InvalidInput, e);
+    }
+    if let Some(url) = &url {
+        if let Err(e) = validate_url(url, &config.webhooks).await {
+            return api::Response::error_description(api::Error::InvalidInput, e);
+        }
+    }
+
+    let events = events.map(|v| v.bits() & WebhookEvents::all().bits());
+    sqlx::query!(
+        r#"update webhook set url = coalesce(?, url), events = coalesce(?, events),
+            secret = coalesce(?, secret) where id = ?"#,
+        url,
+        events,
+        secret,
+        webhook_id
+    )
+    .execute(&db)
+    .await
+    .expect("update webhook");
+
+    match fetch_webhook(id, webhook_id, &db).await {
+        Ok(v) => api::Response::Success(v),
+        Err(e) => api::Response::error(e),
+    }
+}
+
+pub async fn delete_webhook(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(WebhookPath { id, webhook_id }): Path<WebhookPath>,
+) -> api::Response {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::WRITE_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_webhook(id, webhook_id, &db).await {
+        return api::Response::error(e);
+    }
+
+    sqlx::query!(
+        "delete from webhook_delivery where webhook_id = ?",
+        webhook_id
+    )
+    .execute(&db)
+    .await
+    .expect("delete webhook deliveries");
+    sqlx::query!("delete from webhook where id = ?", webhook_id)
+        .execute(&db)
+        .await
+        .expect("delete webhook");
+
+    api::Response::Success(api::EmptyErrorData)
+}
+
+pub async fn list_deliveries(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(WebhookPath { id, webhook_id }): Path<WebhookPath>,
+    Query(DeliveryListQuery { limit, skip }): Query<<ListDeliveries as Endpoint>::Query>,
+) -> api::Response<<ListDeliveries as Endpoint>::Response> {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::READ_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_webhook(id, webhook_id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let limit = match limit {
+        0 => 50,
+        v @ 1..=50 => v,
+        _ => return api::Response::error(api::Error::InvalidInput),
+    };
+    let Some(offset) = skip.checked_mul(limit) else {
+        return api::Response::error(api::Error::InvalidInput);
+    };
+
+    let list = sqlx::query_as!(
+        DeliveryRow,
+        r#"select id, webhook_id, event, status, attempts, created_at, last_attempt_at,
+            response_status, error from webhook_delivery
+            where webhook_id = ? order by id desc limit ? offset ?"#,
+        webhook_id,
+        limit,
+        offset
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(DeliveryInfo::from)
+    .collect();
+
+    api::Response::Success(list)
+}
+
+pub async fn redeliver(
+    State(AppState { db, .. }): State<AppState>,
+    AuthorizedUser { user, token }: AuthorizedUser,
+    Path(DeliveryPath {
+        id,
+        webhook_id,
+        delivery_id,
+    }): Path<DeliveryPath>,
+) -> api::Response<<Redeliver as Endpoint>::Response> {
+    if let Err(e) = require_owner(id, &user, &token, UserTokenScope::WRITE_PROJECTS, &db).await {
+        return api::Response::error(e);
+    }
+    if let Err(e) = fetch_webhook(id, webhook_id, &db).await {
+        return api::Response::error(e);
+    }
+
+    let now = timestamp();
+    let new_id = sqlx::query!(
+        r#"insert into webhook_delivery(webhook_id,event,payload,created_at,run_after)
+            select webhook_id, event, payload, ?, ? from webhook_delivery where id = ? and webhook_id = ?"#,
+        now,
+        now,
+        delivery_id,
+        webhook_id
+    )
+    .execute(&db)
+    .await
+    .expect("copy webhook delivery");
+    if new_id.rows_affected() == 0 {
+        return api::Response::error(api::Error::NotFound);
+    }
+
+    api::Response::Success(fetch_delivery(new_id.last_insert_rowid(), &db).await)
+}
@@ -0,0 +1,268 @@
+//! Storage of project sources
+//!
+//! Every source revision lives in `papers_path/<project_id>/<source_id>/`.
+
+use std::{
+    fs::File,
+    io::{self, Cursor, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use dp_core::v1::project::SourceFormat;
+use flate2::read::GzDecoder;
+
+use crate::config::Config;
+
+/// Directory of all project's revisions
+pub fn project_dir(config: &Config, project_id: i64) -> PathBuf {
+    Path::new(&config.papers_path).join(project_id.to_string())
+}
+
+/// Directory of source revision
+pub fn source_dir(config: &Config, project_id: i64, source_id: i64) -> PathBuf {
+    project_dir(config, project_id).join(source_id.to_string())
+}
+
+/// Path of uploaded archive
+pub fn archive_path(
+    config: &Config,
+    project_id: i64,
+    source_id: i64,
+    format: SourceFormat,
+) -> PathBuf {
+    source_dir(config, project_id, source_id).join(format!("source.{}", format.extension()))
+}
+
+/// Scratch directory where source revision is built
+pub fn scratch_dir(config: &Config, project_id: i64, source_id: i64) -> PathBuf {
+    source_dir(config, project_id, source_id).join("build")
+}
+
+/// Path of built PDF
+pub fn pdf_path(config: &Config, project_id: i64, source_id: i64) -> PathBuf {
+    source_dir(config, project_id, source_id).join("paper.pdf")
+}
+
+/// Path of build log
+pub fn log_path(config: &Config, project_id: i64, source_id: i64) -> PathBuf {
+    source_dir(config, project_id, source_id).join("build.log")
+}
+
+/// Checks that path in archive is relative and does not leave destination
+fn is_safe_path(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Limits of unpacked source archive, see [`Config::max_unpacked_size`]
+/// and [`Config::max_archive_entries`]
+#[derive(Clone, Copy)]
+pub struct UnpackLimits {
+    pub size: u64,
+    pub entries: usize,
+}
+
+impl UnpackLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            size: config.max_unpacked_size,
+            entries: config.max_archive_entries,
+        }
+    }
+}
+
+/// Counts entries and bytes of archive while it is read
+struct Unpacked {
+    limits: UnpackLimits,
+    entries: usize,
+    size: u64,
+}
+
+impl Unpacked {
+    fn new(limits: UnpackLimits) -> Self {
+        Self {
+            limits,
+            entries: 0,
+            size: 0,
+        }
+    }
+
+    /// Returns `false` if archive has too many entries
+    fn add_entry(&mut self) -> bool {
+        self.entries += 1;
+        self.entries <= self.limits.entries
+    }
+
+    /// Returns `false` if unpacked archive is too large
+    fn add_size(&mut self, size: u64) -> bool {
+        self.size = self.size.saturating_add(size);
+        self.size <= self.limits.size
+    }
+
+    /// Bytes left before archive is too large
+    fn remaining(&self) -> u64 {
+        self.limits.size.saturating_sub(self.size)
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::FileTooLarge, "archive exceeds unpack limits")
+}
+
+const MALFORMED: &str = "archive is malformed or contains unsafe entries";
+const TOO_LARGE: &str = "unpacked archive is too large or has too many entries";
+
+fn validate_tar(data: impl Read, limits: UnpackLimits) -> Result<(), &'static str> {
+    let mut archive = tar::Archive::new(data);
+    let Ok(entries) = archive.entries() else {
+        return Err(MALFORMED);
+    };
+
+    let mut unpacked = Unpacked::new(limits);
+    for entry in entries {
+        let Ok(entry) = entry else {
+            return Err(MALFORMED);
+        };
+        if !entry.header().entry_type().is_file() && !entry.header().entry_type().is_dir() {
+            return Err(MALFORMED);
+        }
+        if !entry.path().map(|p| is_safe_path(&p)).unwrap_or_default() {
+            return Err(MALFORMED);
+        }
+        // Size of tar entry is its actual size, reader of entry is limited
+        // by it
+        if !unpacked.add_entry() || !unpacked.add_size(entry.size()) {
+            return Err(TOO_LARGE);
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_zip(data: &[u8], limits: UnpackLimits) -> Result<(), &'static str> {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(data)) else {
+        return Err(MALFORMED);
+    };
+
+    let mut unpacked = Unpacked::new(limits);
+    for i in 0..archive.len() {
+        let Ok(file) = archive.by_index(i) else {
+            return Err(MALFORMED);
+        };
+        let is_symlink = file
+            .unix_mode()
+            .map(|mode| mode & 0o170000 == 0o120000)
+            .unwrap_or_default();
+        if is_symlink || file.enclosed_name().is_none() {
+            return Err(MALFORMED);
+        }
+        // Declared size only, actual size is checked on extraction
+        if !unpacked.add_entry() || !unpacked.add_size(file.size()) {
+            return Err(TOO_LARGE);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that archive is readable, contains only regular files and
+/// directories with relative paths and fits in [`UnpackLimits`].
+pub fn validate_archive(
+    format: SourceFormat,
+    data: &[u8],
+    limits: UnpackLimits,
+) -> Result<(), &'static str> {
+    match format {
+        SourceFormat::Tar => validate_tar(data, limits),
+        SourceFormat::TarGz => validate_tar(GzDecoder::new(data), limits),
+        SourceFormat::Zip => validate_zip(data, limits),
+    }
+}
+
+fn extract_tar(data: impl Read, dest: &Path, limits: UnpackLimits) -> io::Result<()> {
+    let mut archive = tar::Archive::new(data);
+    let mut unpacked = Unpacked::new(limits);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !unpacked.add_entry() || !unpacked.add_size(entry.size()) {
+            return Err(too_large());
+        }
+        // Permissions of directories are not kept, read-only directory
+        // would prevent extraction of its files
+        if entry.header().entry_type().is_dir() {
+            std::fs::create_dir_all(dest.join(entry.path()?))?;
+        } else {
+            entry.unpack_in(dest)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(file: File, dest: &Path, limits: UnpackLimits) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    let mut unpacked = Unpacked::new(limits);
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        if !unpacked.add_entry() {
+            return Err(too_large());
+        }
+        let Some(path) = file.enclosed_name().map(|v| dest.join(v)) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsafe path in archive",
+            ));
+        };
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Declared size of entry can be smaller than its actual size
+        let mut out = File::create(&path)?;
+        let written = io::copy(
+            &mut (&mut file).take(unpacked.remaining().saturating_add(1)),
+            &mut out,
+        )?;
+        if !unpacked.add_size(written) {
+            return Err(too_large());
+        }
+    }
+    Ok(())
+}
+
+/// Extracts archive into `dest`. Archive should be checked by
+/// [`validate_archive`] before. Returns error of kind
+/// [`io::ErrorKind::FileTooLarge`] if archive exceeds `limits`, `dest` can
+/// be partially filled then.
+pub fn extract_archive(
+    format: SourceFormat,
+    archive: &Path,
+    dest: &Path,
+    limits: UnpackLimits,
+) -> io::Result<()> {
+    let file = File::open(archive)?;
+    std::fs::create_dir_all(dest)?;
+    match format {
+        SourceFormat::Tar => extract_tar(file, dest, limits),
+        SourceFormat::TarGz => extract_tar(GzDecoder::new(file), dest, limits),
+        SourceFormat::Zip => extract_zip(file, dest, limits),
+    }
+}
+
+/// Returns total size of files in directory, symlinks are not followed
+pub fn disk_usage(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
+            size += disk_usage(&entry.path())?;
+        } else if ty.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
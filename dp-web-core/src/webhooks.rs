@@ -0,0 +1,366 @@
+//! Delivery of project webhooks
+//!
+//! [`enqueue`] stores event as delivery for every webhook of project
+//! subscribed to it, deliveries are kept as log of webhook. Worker started
+//! with [`spawn`] sends pending deliveries as described in
+//! [`dp_core::v1::webhook`] and retries failed ones with exponential
+//! backoff.
+//!
+//! Unless [`WebhookConfig::allow_private`] is set, webhooks are sent only to
+//! public addresses. Host is checked with [`check_url`] when webhook is
+//! saved and resolved again on every delivery, as DNS records can change.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use dp_core::v1::webhook::{self, DeliveryStatus, Event, Payload};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Url,
+};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use tokio::{
+    net::lookup_host,
+    task::{JoinHandle, JoinSet},
+};
+
+use crate::{config::WebhookConfig, routes::AppState, timestamp};
+
+/// How often pending deliveries are checked
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of deliveries sent at once
+const BATCH_SIZE: i64 = 16;
+
+/// Error of delivery shown to owners of project. Details of failed request
+/// are only logged, so webhooks can't be used to probe network of server.
+const REQUEST_FAILED: &str = "request failed";
+
+/// Special-purpose IPv4 ranges, see RFC 6890 and IANA registry
+const SPECIAL_V4: &[(Ipv4Addr, u32)] = &[
+    // "This network"
+    (Ipv4Addr::new(0, 0, 0, 0), 8),
+    (Ipv4Addr::new(10, 0, 0, 0), 8),
+    // Shared address space of carrier-grade NAT
+    (Ipv4Addr::new(100, 64, 0, 0), 10),
+    (Ipv4Addr::new(127, 0, 0, 0), 8),
+    (Ipv4Addr::new(169, 254, 0, 0), 16),
+    (Ipv4Addr::new(172, 16, 0, 0), 12),
+    // IETF protocol assignments
+    (Ipv4Addr::new(192, 0, 0, 0), 24),
+    (Ipv4Addr::new(192, 0, 2, 0), 24),
+    // 6to4 relay anycast
+    (Ipv4Addr::new(192, 88, 99, 0), 24),
+    (Ipv4Addr::new(192, 168, 0, 0), 16),
+    // Benchmarking
+    (Ipv4Addr::new(198, 18, 0, 0), 15),
+    (Ipv4Addr::new(198, 51, 100, 0), 24),
+    (Ipv4Addr::new(203, 0, 113, 0), 24),
+    // Multicast
+    (Ipv4Addr::new(224, 0, 0, 0), 4),
+    // Reserved, including broadcast
+    (Ipv4Addr::new(240, 0, 0, 0), 4),
+];
+
+/// Special-purpose IPv6 ranges, ranges that embed IPv4 address are checked
+/// with [`embedded_v4`]
+const SPECIAL_V6: &[(Ipv6Addr, u32)] = &[
+    // Unspecified, loopback and deprecated IPv4-compatible addresses
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0), 96),
+    // Local-use NAT64
+    (Ipv6Addr::new(0x64, 0xff9b, 1, 0, 0, 0, 0, 0), 48),
+    // Discard-only
+    (Ipv6Addr::new(0x100, 0, 0, 0, 0, 0, 0, 0), 64),
+    // IETF protocol assignments, including Teredo
+    (Ipv6Addr::new(0x2001, 0, 0, 0, 0, 0, 0, 0), 23),
+    (Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0), 32),
+    (Ipv6Addr::new(0x3fff, 0, 0, 0, 0, 0, 0, 0), 20),
+    // Unique local
+    (Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0), 7),
+    // Link-local and deprecated site-local
+    (Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 10),
+    (Ipv6Addr::new(0xfec0, 0, 0, 0, 0, 0, 0, 0), 10),
+    // Multicast
+    (Ipv6Addr::new(0xff00, 0, 0, 0, 0, 0, 0, 0), 8),
+];
+
+/// IPv4-mapped addresses
+const MAPPED_V4: (Ipv6Addr, u32) = (Ipv6Addr::new(0, 0, 0, 0, 0, 0xffff, 0, 0), 96);
+/// NAT64 well-known prefix, IPv4 address is in the last 32 bits
+const NAT64: (Ipv6Addr, u32) = (Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0), 96);
+/// 6to4, IPv4 address follows the prefix
+const SIX_TO_FOUR: (Ipv6Addr, u32) = (Ipv6Addr::new(0x2002, 0, 0, 0, 0, 0, 0, 0), 16);
+
+fn in_v4(ip: Ipv4Addr, (net, len): (Ipv4Addr, u32)) -> bool {
+    let mask = u32::MAX.checked_shl(32 - len).unwrap_or(0);
+    u32::from(ip) & mask == u32::from(net)
+}
+
+fn in_v6(ip: Ipv6Addr, (net, len): (Ipv6Addr, u32)) -> bool {
+    let mask = u128::MAX.checked_shl(128 - len).unwrap_or(0);
+    u128::from(ip) & mask == u128::from(net)
+}
+
+/// IPv4 address that IPv6 address is translated to
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let bits = u128::from(ip);
+    if in_v6(ip, MAPPED_V4) || in_v6(ip, NAT64) {
+        Some(Ipv4Addr::from(bits as u32))
+    } else if in_v6(ip, SIX_TO_FOUR) {
+        Some(Ipv4Addr::from((bits >> 80) as u32))
+    } else {
+        None
+    }
+}
+
+/// Returns `false` for addresses of special-purpose ranges, such as
+/// loopback, private, shared, link-local, reserved and multicast ones.
+/// Addresses that embed IPv4 address are checked by that address.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v) => !SPECIAL_V4.iter().any(|range| in_v4(v, *range)),
+        IpAddr::V6(v) => match embedded_v4(v) {
+            Some(v) => is_public(v.into()),
+            None => !SPECIAL_V6.iter().any(|range| in_v6(v, *range)),
+        },
+    }
+}
+
+/// Address of host of URL if it is IP address
+fn literal_ip(url: &Url) -> Option<IpAddr> {
+    // IPv6 hosts are enclosed in brackets
+    url.host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Resolves host of webhook, fails if any of its addresses is not public
+async fn resolve_public(host: &str) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = lookup_host((host, 0))
+        .await
+        .map_err(|e| format!("failed to resolve {host}: {e}"))?
+        .collect();
+    match addrs.iter().find(|v| !is_public(v.ip())) {
+        Some(v) => Err(format!("{host} resolves to non-public address {}", v.ip())),
+        None if addrs.is_empty() => Err(format!("{host} has no addresses")),
+        None => Ok(addrs),
+    }
+}
+
+/// Resolver of HTTP client, so connections are made only to checked
+/// addresses
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Checks that webhooks can be sent to host of URL
+pub async fn check_url(url: &Url, config: &WebhookConfig) -> bool {
+    if config.allow_private {
+        return true;
+    }
+    match (literal_ip(url), url.host_str()) {
+        (Some(ip), _) => is_public(ip),
+        (None, Some(host)) => resolve_public(host).await.is_ok(),
+        (None, None) => false,
+    }
+}
+
+/// Creates deliveries of `event` for webhooks of project subscribed to it
+pub async fn enqueue(conn: &mut SqliteConnection, project_id: i64, event: Event) {
+    let kind = event.kind().bits();
+    let webhooks = sqlx::query!(
+        "select id from webhook where project_id = ? and events & ? != 0",
+        project_id,
+        kind
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .expect("select webhooks of project");
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let name = event.name();
+    let now = timestamp();
+    let payload = serde_json::to_string(&Payload {
+        project_id,
+        timestamp: now,
+        event,
+    })
+    .expect("serialize webhook payload");
+
+    for webhook in webhooks {
+        sqlx::query!(
+            "insert into webhook_delivery(webhook_id,event,payload,created_at,run_after) values (?,?,?,?,?)",
+            webhook.id,
+            name,
+            payload,
+            now,
+            now
+        )
+        .execute(&mut *conn)
+        .await
+        .expect("insert webhook delivery");
+    }
+}
+
+struct Delivery {
+    id: i64,
+    url: String,
+    secret: String,
+    event: String,
+    payload: String,
+    attempts: i64,
+}
+
+/// Sends delivery once, returns status of response and error if attempt
+/// failed
+async fn attempt(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    delivery: &Delivery,
+) -> (Option<i64>, Option<String>) {
+    let blocked = Url::parse(&delivery.url)
+        .ok()
+        .and_then(|v| literal_ip(&v))
+        .is_some_and(|v| !config.allow_private && !is_public(v));
+    if blocked {
+        eprintln!(
+            "Webhook delivery {} to non-public address is blocked",
+            delivery.id
+        );
+        return (None, Some(REQUEST_FAILED.to_owned()));
+    }
+
+    let signature = webhook::sign(&delivery.secret, timestamp(), delivery.payload.as_bytes());
+    let res = client
+        .post(&delivery.url)
+        .header("content-type", "application/json")
+        .header(webhook::EVENT_HEADER, &delivery.event)
+        .header(webhook::DELIVERY_HEADER, delivery.id)
+        .header(webhook::SIGNATURE_HEADER, signature)
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match res {
+        Ok(v) if v.status().is_success() => (Some(v.status().as_u16() as i64), None),
+        Ok(v) => (
+            Some(v.status().as_u16() as i64),
+            Some(format!("receiver responded with {}", v.status())),
+        ),
+        Err(e) => {
+            eprintln!("Webhook delivery {} failed: {e:?}", delivery.id);
+            (None, Some(REQUEST_FAILED.to_owned()))
+        }
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    db: Pool<Sqlite>,
+    config: &WebhookConfig,
+    delivery: Delivery,
+) {
+    let (response_status, error) = attempt(&client, config, &delivery).await;
+    let attempts = delivery.attempts + 1;
+    let now = timestamp();
+
+    let (status, run_after) = match &error {
+        None => (DeliveryStatus::Succeeded, now),
+        Some(_) if attempts < config.max_attempts => {
+            let delay =
+                (config.retry_delay * 1000).saturating_mul(1 << (attempts - 1).clamp(0, 16)) as i64;
+            (DeliveryStatus::Pending, now + delay)
+        }
+        Some(e) => {
+            eprintln!("Failed to deliver webhook delivery {}: {e}", delivery.id);
+            (DeliveryStatus::Failed, now)
+        }
+    };
+
+    let status = status as i64;
+    sqlx::query!(
+        r#"update webhook_delivery set status = ?, attempts = ?, run_after = ?, last_attempt_at = ?,
+            response_status = ?, error = ? where id = ?"#,
+        status,
+        attempts,
+        run_after,
+        now,
+        response_status,
+        error,
+        delivery.id
+    )
+    .execute(&db)
+    .await
+    .expect("update webhook delivery");
+}
+
+/// Sends due deliveries, returns `true` if there can be more of them
+async fn process(
+    client: &reqwest::Client,
+    db: &Pool<Sqlite>,
+    config: &'static WebhookConfig,
+) -> bool {
+    let pending = DeliveryStatus::Pending as i64;
+    let now = timestamp();
+    let due = sqlx::query_as!(
+        Delivery,
+        r#"select webhook_delivery.id, webhook.url, webhook.secret, webhook_delivery.event,
+                webhook_delivery.payload, webhook_delivery.attempts
+            from webhook_delivery
+            join webhook on webhook_delivery.webhook_id = webhook.id
+            where webhook_delivery.status = ? and webhook_delivery.run_after <= ?
+            order by webhook_delivery.id limit ?"#,
+        pending,
+        now,
+        BATCH_SIZE
+    )
+    .fetch_all(db)
+    .await
+    .expect("select due webhook deliveries");
+
+    let full = due.len() as i64 == BATCH_SIZE;
+    let mut tasks = JoinSet::new();
+    for delivery in due {
+        tasks.spawn(deliver(client.clone(), db.clone(), config, delivery));
+    }
+    while tasks.join_next().await.is_some() {}
+    full
+}
+
+/// Spawns task that sends pending deliveries
+pub fn spawn(state: &AppState) -> JoinHandle<()> {
+    let AppState { config, db, .. } = state.clone();
+    // Receiver can't redirect signed payload to other URL, proxies are not
+    // used, so resolved addresses are checked
+    let mut client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.webhooks.timeout))
+        .redirect(Policy::none())
+        .no_proxy();
+    if !config.webhooks.allow_private {
+        client = client.dns_resolver(Arc::new(PublicResolver));
+    }
+    let client = client.build().expect("build HTTP client");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            while process(&client, &db, &config.webhooks).await {}
+        }
+    })
+}
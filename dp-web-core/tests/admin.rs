@@ -0,0 +1,136 @@
+//! Administrators can't demote themselves or the last administrator
+
+use axum::Router;
+use dp_core::v1::{
+    endpoint::admin::UpdateUserBody,
+    user::{UserRole, UserTokenScope, UserTokenTy},
+};
+use dp_web_core::{
+    build::queue::BuildQueue,
+    config::Config,
+    oidc::Provider,
+    routes::{
+        v1::auth::{issue_scoped_token, TokenParams},
+        AppState,
+    },
+};
+use serde_json::Value;
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use tokio::net::TcpListener;
+
+struct Server {
+    url: String,
+    config: &'static Config,
+    db: SqlitePool,
+}
+
+impl Server {
+    async fn start() -> Self {
+        // Every connection has its own in-memory database
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        dp_web_core::apply_migrations(&db).await.unwrap();
+
+        let config: &'static Config = Box::leak(Box::new(
+            serde_yaml::from_str(&format!(
+                "papers_path: {}\ntoken_key: test",
+                std::env::temp_dir().display()
+            ))
+            .unwrap(),
+        ));
+        let app = Router::new()
+            .nest("/v1", dp_web_core::routes::v1::get_routes())
+            .with_state(AppState {
+                config,
+                db: db.clone(),
+                builds: BuildQueue::default(),
+                oidc: Provider::default(),
+            });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self { url, config, db }
+    }
+
+    /// Creates administrator, returns its `Authorization` header
+    async fn admin(&self, username: &str) -> String {
+        let id = sqlx::query("insert into user(ty,username,role) values (2,?,2)")
+            .bind(username)
+            .execute(&self.db)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+        let params = TokenParams {
+            ty: UserTokenTy::Personal,
+            scope: UserTokenScope::all(),
+            label: None,
+            lifetime: 60 * 1000,
+            family: None,
+        };
+        let token = issue_scoped_token(id, params, self.config, &self.db).await;
+        format!("Bearer {}:{}", token.user_id, token.token)
+    }
+
+    async fn set_role(&self, auth: &str, id: i64, role: UserRole) -> bool {
+        let body = UpdateUserBody {
+            role: Some(role),
+            ..Default::default()
+        };
+        let res: Value = reqwest::Client::new()
+            .patch(format!("{}/v1/admin/users/{id}", self.url))
+            .header("authorization", auth)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        res["ok"] == true
+    }
+
+    async fn admins(&self) -> i64 {
+        sqlx::query_scalar("select count(*) from user where role = 2 and suspended_at is null")
+            .fetch_one(&self.db)
+            .await
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn last_admin_is_not_demoted() {
+    let server = Server::start().await;
+    let alice = server.admin("alice").await;
+    server.admin("bob").await;
+    server.admin("carol").await;
+    sqlx::query("update user set suspended_at = 1 where username = 'carol'")
+        .execute(&server.db)
+        .await
+        .unwrap();
+
+    // Not themselves
+    assert!(!server.set_role(&alice, 1, UserRole::User).await);
+
+    // Others while alice remains
+    assert!(server.set_role(&alice, 2, UserRole::Moderator).await);
+    assert!(server.set_role(&alice, 3, UserRole::User).await);
+    assert_eq!(server.admins().await, 1);
+}
+
+#[tokio::test]
+async fn concurrent_demotions_leave_admin() {
+    let server = Server::start().await;
+    let alice = server.admin("alice").await;
+    let bob = server.admin("bob").await;
+
+    let (first, second) = tokio::join!(
+        server.set_role(&alice, 2, UserRole::User),
+        server.set_role(&bob, 1, UserRole::User)
+    );
+    assert!(first != second, "{first} {second}");
+    assert_eq!(server.admins().await, 1);
+}
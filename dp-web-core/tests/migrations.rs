@@ -0,0 +1,43 @@
+//! Upgrade of database created before builds were introduced
+
+use dp_core::v1::project::BuildStatus;
+use dp_web_core::build::queue;
+use sqlx::sqlite::SqlitePoolOptions;
+
+#[tokio::test]
+async fn old_sources_are_not_rebuilt() {
+    // Every connection has its own in-memory database
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    for migration in [
+        include_str!("../src/migrations/0001-initial.sql"),
+        include_str!("../src/migrations/0002-project-source.sql"),
+    ] {
+        sqlx::query(migration).execute(&db).await.unwrap();
+    }
+    sqlx::query(
+        r#"insert into user(username,telegram_id) values ('alice',1);
+        insert into project(title,author_id) values ('Paper',1);
+        insert into project_source(project_id,created_at) values (1,0);"#,
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    dp_web_core::apply_migrations(&db).await.unwrap();
+    queue::recover(&db).await;
+
+    let (status,): (i64,) = sqlx::query_as("select build_status from project_source")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert!(BuildStatus::from_bits(status) == BuildStatus::NotBuilt);
+    let (jobs,): (i64,) = sqlx::query_as("select count(*) from build_job")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(jobs, 0);
+}
@@ -0,0 +1,300 @@
+//! OpenID Connect login against mock identity provider
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Redirect,
+    routing::{get, post},
+    Form, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use dp_web_core::{build::queue::BuildQueue, config::Config, oidc::Provider, routes::AppState};
+use reqwest::{header, redirect, Url};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tokio::net::TcpListener;
+
+/// Mock identity provider. User picks account with `login_hint`, which is
+/// also authorization code. Code `bad` is rejected, ID token issued for
+/// code `code-mallory` has nonce of another login.
+#[derive(Clone)]
+struct Idp {
+    issuer: String,
+    accounts: Arc<HashMap<&'static str, (&'static str, &'static str)>>,
+    /// PKCE challenge and nonce of issued codes
+    codes: Arc<Mutex<HashMap<String, (String, String)>>>,
+}
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn authorize(
+    State(idp): State<Idp>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Redirect {
+    assert_eq!(query["code_challenge_method"], "S256");
+    let code = &query["login_hint"];
+    idp.codes.lock().unwrap().insert(
+        code.clone(),
+        (query["code_challenge"].clone(), query["nonce"].clone()),
+    );
+    let mut url = Url::parse(&query["redirect_uri"]).unwrap();
+    url.query_pairs_mut()
+        .append_pair("code", code)
+        .append_pair("state", &query["state"]);
+    Redirect::to(url.as_str())
+}
+
+async fn token(
+    State(idp): State<Idp>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+    let code = form["code"].as_str();
+    let issued = idp.codes.lock().unwrap().remove(code);
+    let (Some((sub, _)), Some((challenge, mut nonce))) = (idp.accounts.get(code), issued) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if form["client_secret"] != "secret"
+        || URL_SAFE_NO_PAD.encode(Sha256::digest(&form["code_verifier"])) != challenge
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if code == "code-mallory" {
+        nonce = "nonce-of-another-login".to_owned();
+    }
+
+    let claims = json!({ "iss": idp.issuer, "sub": sub, "aud": "dp", "nonce": nonce });
+    let id_token = format!("e30.{}.", URL_SAFE_NO_PAD.encode(claims.to_string()));
+    Ok(Json(
+        json!({ "access_token": code, "token_type": "Bearer", "id_token": id_token }),
+    ))
+}
+
+async fn userinfo(State(idp): State<Idp>, headers: HeaderMap) -> Json<Value> {
+    let token = headers["authorization"].to_str().unwrap();
+    let (sub, username) = idp.accounts[token.trim_start_matches("Bearer ")];
+    Json(json!({ "sub": sub, "preferred_username": username }))
+}
+
+async fn mock_idp(accounts: HashMap<&'static str, (&'static str, &'static str)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+    let metadata = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+    });
+    let idp = Idp {
+        issuer: issuer.clone(),
+        accounts: Arc::new(accounts),
+        codes: Arc::default(),
+    };
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(move || async move { Json(metadata) }),
+        )
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/userinfo", get(userinfo))
+        .with_state(idp);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    issuer
+}
+
+/// Starts server with database `name`, tests run in the same process
+async fn server(issuer: &str, name: &str) -> (String, SqlitePool) {
+    let path =
+        std::env::temp_dir().join(format!("dp-oidc-test-{name}-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = SqlitePool::connect(&format!("sqlite://{}?mode=rwc", path.display()))
+        .await
+        .unwrap();
+    dp_web_core::apply_migrations(&db).await.unwrap();
+
+    let config: Config = serde_yaml::from_str(&format!(
+        r#"
+papers_path: {}
+token_key: test
+oidc:
+  issuer: {issuer}
+  client_id: dp
+  client_secret: secret
+  redirect_uri: http://frontend/oidc
+"#,
+        std::env::temp_dir().display()
+    ))
+    .unwrap();
+    let state = AppState {
+        config: Box::leak(Box::new(config)),
+        db: db.clone(),
+        builds: BuildQueue::default(),
+        oidc: Provider::default(),
+    };
+    let app = Router::new()
+        .nest("/v1", dp_web_core::routes::v1::get_routes())
+        .with_state(state);
+    (serve(app).await, db)
+}
+
+/// Login started in browser
+struct Login {
+    /// Value of state cookie set by server
+    cookie: String,
+    /// Parameters passed by provider to `redirect_uri`
+    code: String,
+    state: String,
+}
+
+/// Starts login and signs in to provider with account of authorization code
+async fn start(server: &str, invite: Option<&str>, code: &str) -> Login {
+    let client = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .build()
+        .unwrap();
+    let mut authorize = client.get(format!("{server}/v1/auth/oidc"));
+    if let Some(invite) = invite {
+        authorize = authorize.query(&[("invite", invite)]);
+    }
+    let res = authorize.send().await.unwrap();
+    let cookie = res.headers()[header::SET_COOKIE]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_owned();
+    assert!(cookie.starts_with("dp_oidc_state="), "{cookie}");
+    let res: Value = res.json().await.unwrap();
+    assert_eq!(res["ok"], true, "{res}");
+
+    let res = client
+        .get(res["result"]["url"].as_str().unwrap())
+        .query(&[("login_hint", code)])
+        .send()
+        .await
+        .unwrap();
+    let location = Url::parse(res.headers()[header::LOCATION].to_str().unwrap()).unwrap();
+    let params: HashMap<_, _> = location.query_pairs().into_owned().collect();
+    Login {
+        cookie,
+        code: params["code"].clone(),
+        state: params["state"].clone(),
+    }
+}
+
+/// Passes parameters of login to server with given cookie, returns result of
+/// callback
+async fn finish(server: &str, login: &Login, cookie: Option<&str>) -> Value {
+    let mut callback = reqwest::Client::new()
+        .post(format!("{server}/v1/auth/oidc"))
+        .json(&json!({ "code": login.code, "state": login.state }));
+    if let Some(cookie) = cookie {
+        callback = callback.header(header::COOKIE, cookie);
+    }
+    callback.send().await.unwrap().json().await.unwrap()
+}
+
+/// Logs in through provider with authorization code, returns result of
+/// callback
+async fn login(server: &str, invite: Option<&str>, code: &str) -> Value {
+    let login = start(server, invite, code).await;
+    finish(server, &login, Some(&login.cookie)).await
+}
+
+async fn user(db: &SqlitePool, id: &Value) -> (i64, String, Option<i64>) {
+    sqlx::query_as("select ty, username, invite_id from user where id = ?")
+        .bind(id.as_i64().unwrap())
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn oidc_login() {
+    let accounts = HashMap::from([
+        ("code-alice", ("sub-alice", "alice")),
+        ("code-alice-again", ("sub-alice", "alice")),
+        ("code-bob", ("sub-bob", "bob")),
+        ("code-carol-1", ("sub-carol", "carol")),
+        ("code-carol-2", ("sub-carol", "carol")),
+    ]);
+    let issuer = mock_idp(accounts).await;
+    let (server, db) = server(&issuer, "login").await;
+
+    sqlx::query(
+        "insert into userinvite(user_ty,reason,invite,issued_at) values (2,'test','inv',0)",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+
+    // First login with invite creates user of invite
+    let res = login(&server, Some("inv"), "code-alice").await;
+    assert_eq!(res["ok"], true, "{res}");
+    let alice = res["result"]["user_id"].clone();
+    assert_eq!(user(&db, &alice).await, (2, "alice".to_owned(), Some(1)));
+
+    // First login without invite creates unverified user
+    let res = login(&server, None, "code-bob").await;
+    assert_eq!(res["ok"], true, "{res}");
+    let bob = res["result"]["user_id"].clone();
+    assert_eq!(user(&db, &bob).await, (1, "bob".to_owned(), None));
+
+    // Next login maps identity to the same user
+    let res = login(&server, None, "code-alice-again").await;
+    assert_eq!(res["ok"], true, "{res}");
+    assert_eq!(res["result"]["user_id"], alice);
+
+    // Rejected code
+    let res = login(&server, None, "bad").await;
+    assert_eq!(res["ok"], false, "{res}");
+
+    // Concurrent first logins of the same account end up with one user
+    let (first, second) = tokio::join!(
+        login(&server, None, "code-carol-1"),
+        login(&server, None, "code-carol-2")
+    );
+    assert_eq!(first["ok"], true, "{first}");
+    assert_eq!(second["ok"], true, "{second}");
+    assert_eq!(first["result"]["user_id"], second["result"]["user_id"]);
+    let (users,): (i64,) = sqlx::query_as("select count(*) from user where username like 'carol%'")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(users, 1);
+}
+
+#[tokio::test]
+async fn oidc_login_is_bound_to_browser() {
+    let accounts = HashMap::from([
+        ("code-alice", ("sub-alice", "alice")),
+        ("code-mallory", ("sub-mallory", "mallory")),
+    ]);
+    let issuer = mock_idp(accounts).await;
+    let (server, _) = server(&issuer, "binding").await;
+
+    // Code and state of victim's login are useless without victim's cookie
+    let victim = start(&server, None, "code-alice").await;
+    let attacker = start(&server, None, "code-alice").await;
+    let res = finish(&server, &victim, None).await;
+    assert_eq!(res["ok"], false, "{res}");
+    let res = finish(&server, &victim, Some(&attacker.cookie)).await;
+    assert_eq!(res["ok"], false, "{res}");
+
+    // ID token must carry nonce of the login
+    let res = login(&server, None, "code-mallory").await;
+    assert_eq!(res["ok"], false, "{res}");
+}
@@ -0,0 +1,86 @@
+//! Source archives are checked against limits of unpacked size
+
+use std::io::{Cursor, Read, Write};
+
+use dp_core::v1::project::SourceFormat;
+use dp_web_core::sources::{extract_archive, validate_archive, UnpackLimits};
+use flate2::{write::GzEncoder, Compression};
+
+const LIMITS: UnpackLimits = UnpackLimits {
+    size: 1024 * 1024,
+    entries: 10,
+};
+
+/// Archive with files of given sizes, every file is filled with zeros
+fn tar_gz(sizes: &[usize]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::best()));
+    for (i, size) in sizes.iter().enumerate() {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(*size as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("{i}.tex"),
+                std::io::repeat(0).take(*size as u64),
+            )
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+fn zip(sizes: &[usize]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (i, size) in sizes.iter().enumerate() {
+        writer
+            .start_file(format!("{i}.tex"), zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(&vec![0; *size]).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn archives_over_limits_are_rejected() {
+    assert!(validate_archive(SourceFormat::TarGz, &tar_gz(&[1000; 3]), LIMITS).is_ok());
+    assert!(validate_archive(SourceFormat::Zip, &zip(&[1000; 3]), LIMITS).is_ok());
+
+    // Compressed archive is small, unpacked one is not
+    let bomb = tar_gz(&[64 * 1024 * 1024]);
+    assert!(bomb.len() < 1024 * 1024);
+    assert!(validate_archive(SourceFormat::TarGz, &bomb, LIMITS).is_err());
+    let bomb = zip(&[2 * 1024 * 1024]);
+    assert!(validate_archive(SourceFormat::Zip, &bomb, LIMITS).is_err());
+
+    // Too many entries
+    assert!(validate_archive(SourceFormat::TarGz, &tar_gz(&[0; 11]), LIMITS).is_err());
+    assert!(validate_archive(SourceFormat::Zip, &zip(&[0; 11]), LIMITS).is_err());
+}
+
+#[test]
+fn extraction_stops_at_limits() {
+    let dir = std::env::temp_dir().join(format!("dp-sources-test-{}", std::process::id()));
+    let small = UnpackLimits {
+        size: 2500,
+        entries: 10,
+    };
+
+    for (format, data) in [
+        (SourceFormat::TarGz, tar_gz(&[1000; 3])),
+        (SourceFormat::Zip, zip(&[1000; 3])),
+    ] {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("source");
+        std::fs::write(&archive, data).unwrap();
+        let dest = dir.join("build");
+
+        extract_archive(format, &archive, &dest, LIMITS).unwrap();
+        assert_eq!(std::fs::read(dest.join("2.tex")).unwrap().len(), 1000);
+
+        let err = extract_archive(format, &archive, &dest, small).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::FileTooLarge);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,174 @@
+//! Webhooks are not sent to private addresses of server network
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{routing::post, Router};
+use dp_web_core::{
+    build::queue::BuildQueue,
+    config::{Config, WebhookConfig},
+    oidc::Provider,
+    routes::AppState,
+    webhooks,
+};
+use reqwest::Url;
+use sqlx::sqlite::SqlitePoolOptions;
+use tokio::net::TcpListener;
+
+fn config(allow_private: bool) -> Config {
+    serde_yaml::from_str(&format!(
+        r#"
+papers_path: {}
+token_key: test
+webhooks:
+  max_attempts: 1
+  allow_private: {allow_private}
+"#,
+        std::env::temp_dir().display()
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn private_urls_are_rejected() {
+    let config = config(false).webhooks;
+    for url in [
+        "http://127.0.0.1/",
+        "http://localhost:8080/",
+        "http://10.1.2.3/",
+        "http://192.168.0.1/",
+        "http://169.254.169.254/latest/meta-data",
+        "http://0.0.0.0/",
+        "http://[::1]/",
+        "http://[::ffff:127.0.0.1]/",
+        "http://[fd00::1]/",
+        "http://[fe80::1]/",
+        // Shared, "this network", IETF, benchmarking and reserved ranges
+        "http://100.64.0.1/",
+        "http://0.1.2.3/",
+        "http://192.0.0.8/",
+        "http://198.18.0.1/",
+        "http://240.0.0.1/",
+        "http://255.255.255.255/",
+        // Multicast
+        "http://224.0.0.1/",
+        "http://[ff02::1]/",
+        // NAT64 and 6to4 addresses of private IPv4 addresses
+        "http://[64:ff9b::7f00:1]/",
+        "http://[64:ff9b::a9fe:a9fe]/",
+        "http://[2002:7f00:1::]/",
+        "http://[2002:c0a8:1::1]/",
+    ] {
+        let url = Url::parse(url).unwrap();
+        assert!(!webhooks::check_url(&url, &config).await, "{url}");
+    }
+
+    for url in [
+        "http://93.184.215.14/hook",
+        "http://[2606:2800:21f:cb07:6820:80da:af6b:8b2c]/hook",
+        // NAT64 address of 93.184.215.14
+        "http://[64:ff9b::5db8:d70e]/hook",
+    ] {
+        let url = Url::parse(url).unwrap();
+        assert!(webhooks::check_url(&url, &config).await, "{url}");
+    }
+
+    let config = WebhookConfig {
+        allow_private: true,
+        ..config
+    };
+    let url = Url::parse("http://127.0.0.1/").unwrap();
+    assert!(webhooks::check_url(&url, &config).await);
+}
+
+/// Starts receiver that counts requests, returns its port
+async fn receiver(received: Arc<AtomicUsize>) -> u16 {
+    let app = Router::new().route(
+        "/hook",
+        post(move || async move {
+            received.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+/// Queues delivery to every URL and waits until all of them are attempted,
+/// returns their statuses and errors
+async fn deliver(allow_private: bool, urls: &[String]) -> Vec<(i64, Option<String>)> {
+    // Every connection has its own in-memory database
+    let db = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    dp_web_core::apply_migrations(&db).await.unwrap();
+    for url in urls {
+        let webhook_id = sqlx::query(
+            "insert into webhook(project_id,url,secret,events,created_at) values (1,?,'secret',1,0)",
+        )
+        .bind(url)
+        .execute(&db)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+        sqlx::query(
+            "insert into webhook_delivery(webhook_id,event,payload,created_at,run_after) values (?,'project_deleted','{}',0,0)",
+        )
+        .bind(webhook_id)
+        .execute(&db)
+        .await
+        .unwrap();
+    }
+
+    let state = AppState {
+        config: Box::leak(Box::new(config(allow_private))),
+        db: db.clone(),
+        builds: BuildQueue::default(),
+        oidc: Provider::default(),
+    };
+    let worker = webhooks::spawn(&state);
+    let result = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let rows: Vec<(i64, Option<String>)> =
+                sqlx::query_as("select status, error from webhook_delivery order by id")
+                    .fetch_all(&db)
+                    .await
+                    .unwrap();
+            if rows.iter().all(|(status, _)| *status != 0) {
+                return rows;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("deliveries attempted");
+    worker.abort();
+    result
+}
+
+#[tokio::test]
+async fn deliveries_to_private_addresses_are_blocked() {
+    let received = Arc::new(AtomicUsize::new(0));
+    let port = receiver(received.clone()).await;
+    let urls = [
+        format!("http://127.0.0.1:{port}/hook"),
+        format!("http://localhost:{port}/hook"),
+    ];
+
+    // Failed, error doesn't tell why request failed
+    let failed = (2, Some("request failed".to_owned()));
+    assert_eq!(deliver(false, &urls).await, [failed.clone(), failed]);
+    assert_eq!(received.load(Ordering::SeqCst), 0);
+
+    let succeeded = (1, None);
+    assert_eq!(deliver(true, &urls).await, [succeeded.clone(), succeeded]);
+    assert_eq!(received.load(Ordering::SeqCst), 2);
+}
@@ -1,7 +1,7 @@
 use std::{
     fs,
     path::PathBuf,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use axum::Router;
@@ -9,8 +9,17 @@ use clap::{
     builder::{OsStr, PossibleValue},
     Parser, Subcommand, ValueEnum,
 };
+use dp_core::v1::user::{UserTokenScope, UserTokenTy};
+use dp_web_core::build::{self, queue::BuildQueue};
 use dp_web_core::config::Config;
-use dp_web_core::routes::v1::models::user::generate_token;
+use dp_web_core::maintenance;
+use dp_web_core::notifications;
+use dp_web_core::oidc;
+use dp_web_core::routes::v1::{
+    auth::{issue_scoped_token, TokenParams},
+    models::user::generate_token,
+};
+use dp_web_core::webhooks;
 use sqlx::SqlitePool;
 
 use dp_web_core::routes::AppState;
@@ -42,9 +51,33 @@ impl ValueEnum for UserTy {
         Some(PossibleValue::new(self.0.as_str()))
     }
 }
-impl Into<OsStr> for UserTy {
-    fn into(self) -> OsStr {
-        OsStr::from(self.0.as_str())
+impl From<UserTy> for OsStr {
+    fn from(value: UserTy) -> Self {
+        OsStr::from(value.0.as_str())
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct UserRole(dp_core::v1::user::UserRole);
+
+impl ValueEnum for UserRole {
+    fn value_variants<'a>() -> &'a [Self] {
+        const N: usize = dp_core::v1::user::UserRole::ALL_VALUES.len();
+        const RES: [UserRole; N] = {
+            let mut v = [UserRole(dp_core::v1::user::UserRole::User); N];
+            let mut i = 0;
+            while i < N {
+                v[i] = UserRole(dp_core::v1::user::UserRole::ALL_VALUES[i]);
+                i += 1;
+            }
+            v
+        };
+
+        &RES
+    }
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.0.as_str()))
     }
 }
 
@@ -79,7 +112,45 @@ enum Subcommands {
         /// Type of user
         #[arg(long, default_value = UserTy(dp_core::v1::user::UserTy::Unregistered))]
         user_type: UserTy,
+
+        /// How many users can register with invite
+        #[arg(long, default_value_t = 1)]
+        max_uses: i64,
+
+        /// Lifetime of invite in seconds, invite never expires if not set
+        #[arg(long)]
+        expires_in: Option<i64>,
+    },
+    /// List active invites
+    ListInvites {
+        /// Also list used, expired and revoked invites
+        #[arg(long)]
+        all: bool,
+    },
+    /// Revoke invite, users already registered with it are kept
+    RevokeInvite {
+        /// Id of invite
+        id: i64,
+    },
+    /// Set role of user
+    SetRole {
+        /// Name of user
+        username: String,
+
+        /// Role of user
+        role: UserRole,
+    },
+    /// Issue short-lived personal token with administration scope
+    IssueAdminToken {
+        /// Name of administrator
+        username: String,
+
+        /// Lifetime of token in seconds
+        #[arg(long, default_value_t = 3600)]
+        lifetime: i64,
     },
+    /// Remove expired tokens, stale invites and orphaned files once
+    Gc,
 }
 
 #[tokio::main]
@@ -103,33 +174,70 @@ async fn main() {
             args.config.to_string_lossy()
         ),
     };
+    if cfg.token_key.is_empty() {
+        panic!(
+            "`token_key` is not set in config file '{}'. It is required since user tokens are \
+            stored as keyed hashes, see config.example.yml",
+            args.config.to_string_lossy()
+        );
+    }
 
     match args.subcommand {
         Subcommands::Start { ip } => {
+            if !cfg.build.sandbox.is_configured() {
+                panic!(
+                    "`build.sandbox.bwrap` is not set in config file '{}'. Compilers are run in \
+                    bubblewrap, set `build.sandbox.unsafe_no_isolation` to run them without it, \
+                    see config.example.yml",
+                    args.config.to_string_lossy()
+                );
+            }
+
+            let state = AppState {
+                config: Box::leak(Box::new(cfg)),
+                db,
+                builds: BuildQueue::default(),
+                oidc: oidc::Provider::default(),
+            };
+
+            build::queue::recover(&state.db).await;
+            build::queue::spawn_workers(&state);
+            maintenance::spawn(&state);
+            notifications::spawn(&state);
+            webhooks::spawn(&state);
+
             let app = Router::new()
                 .nest("/v1", dp_web_core::routes::v1::get_routes())
-                .with_state(AppState {
-                    config: Box::leak(Box::new(cfg)),
-                    db,
-                });
+                .with_state(state);
 
             println!("Server starting at {ip}");
             let listener = tokio::net::TcpListener::bind(ip).await.unwrap();
             axum::serve(listener, app).await.unwrap();
         }
-        Subcommands::CreateInvite { reason, user_type } => {
+        Subcommands::CreateInvite {
+            reason,
+            user_type,
+            max_uses,
+            expires_in,
+        } => {
+            if max_uses < 1 {
+                panic!("Maximum number of uses should be positive");
+            }
             let token = generate_token();
             let issued_at = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as i64;
+            let expires_at = expires_in.map(|v| issued_at + v * 1000);
             let user_type = user_type.0 as i64;
             let res = sqlx::query!(
-                "insert into userinvite(user_ty,reason,invite,issued_at) values (?,?,?,?)",
+                "insert into userinvite(user_ty,reason,invite,issued_at,expires_at,max_uses) values (?,?,?,?,?,?)",
                 user_type,
                 reason,
                 token,
-                issued_at
+                issued_at,
+                expires_at,
+                max_uses
             )
             .execute(&db)
             .await;
@@ -138,5 +246,105 @@ async fn main() {
                 Err(e) => panic!("Failed to insert to database: {e}"),
             }
         }
+        Subcommands::ListInvites { all } => {
+            let now = dp_web_core::timestamp();
+            let invites = sqlx::query!(
+                r#"select userinvite.*, user.username as "creator?"
+                    from userinvite
+                    left join user on userinvite.created_by = user.id
+                    where ? or (revoked_at is null and (expires_at is null or expires_at > ?) and uses < max_uses)
+                    order by userinvite.id"#,
+                all,
+                now
+            )
+            .fetch_all(&db)
+            .await
+            .expect("select invites");
+
+            let date =
+                |v: i64| httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_millis(v as u64));
+            for v in invites {
+                let state = if v.revoked_at.is_some() {
+                    "revoked"
+                } else if v.expires_at.is_some_and(|e| e <= now) {
+                    "expired"
+                } else if v.uses >= v.max_uses {
+                    "used"
+                } else {
+                    "active"
+                };
+                println!(
+                    "{}\t{}\t{}\t{}/{}\tissued {} by {}\texpires {}\t{}\t{}",
+                    v.id,
+                    dp_core::v1::user::UserTy::from_bits(v.user_ty).as_str(),
+                    state,
+                    v.uses,
+                    v.max_uses,
+                    date(v.issued_at),
+                    v.creator.as_deref().unwrap_or("cli"),
+                    v.expires_at.map(date).unwrap_or_else(|| "never".to_owned()),
+                    v.invite,
+                    v.reason,
+                );
+            }
+        }
+        Subcommands::RevokeInvite { id } => {
+            let revoked_at = dp_web_core::timestamp();
+            let res = sqlx::query!(
+                "update userinvite set revoked_at = coalesce(revoked_at, ?) where id = ?",
+                revoked_at,
+                id
+            )
+            .execute(&db)
+            .await;
+            match res {
+                Ok(v) if v.rows_affected() == 0 => println!("Invite {id} not found"),
+                Ok(_) => println!("Invite {id} revoked"),
+                Err(e) => panic!("Failed to update database: {e}"),
+            }
+        }
+        Subcommands::SetRole { username, role } => {
+            let irole = role.0 as i64;
+            let res = sqlx::query!(
+                "update user set role = ? where username = ?",
+                irole,
+                username
+            )
+            .execute(&db)
+            .await;
+            match res {
+                Ok(v) if v.rows_affected() == 0 => println!("User {username} not found"),
+                Ok(_) => println!("User {username} is {}", role.0.as_str()),
+                Err(e) => panic!("Failed to update database: {e}"),
+            }
+        }
+        Subcommands::IssueAdminToken { username, lifetime } => {
+            let user = sqlx::query!("select id, role from user where username = ?", username)
+                .fetch_optional(&db)
+                .await
+                .expect("select user");
+            let Some(user) = user else {
+                panic!("User {username} not found");
+            };
+            if dp_core::v1::user::UserRole::from_bits(user.role)
+                != dp_core::v1::user::UserRole::Admin
+            {
+                panic!("User {username} is not admin");
+            }
+
+            let params = TokenParams {
+                ty: UserTokenTy::Personal,
+                scope: UserTokenScope::all(),
+                label: Some("admin"),
+                lifetime: lifetime * 1000,
+                family: None,
+            };
+            let res = issue_scoped_token(user.id, params, &cfg, &db).await;
+            println!("Admin token: {}:{}", res.user_id, res.token);
+        }
+        Subcommands::Gc => match maintenance::run_gc(&cfg, &db).await {
+            Ok(report) => println!("Removed {report}"),
+            Err(e) => panic!("Failed to remove orphaned files: {e}"),
+        },
     }
 }